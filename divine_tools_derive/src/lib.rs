@@ -0,0 +1,396 @@
+//! Derive macros for the `Binary` and `Inspector` traits of `divine_tools`.
+//!
+//! The generated code refers to `crate::...` paths, so the macros only work
+//! inside the `divine_tools` crate itself.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Expr, Fields, Ident, LitStr, Type, parse_macro_input, spanned::Spanned,
+};
+
+/// Reads and writes the fields in declaration order.
+///
+/// Field attributes:
+/// - `#[binary(len = u8)]`: `Vec` prefixed by its length as the given
+///   integer type instead of `u32`.
+/// - `#[binary(count = N)]`: `Vec` of exactly `N` elements without a length prefix.
+/// - `#[binary(pad = N)]`: `N` unused bytes after the field, written as zeros.
+///
+/// Enums must have only unit variants and a `#[binary(repr = u8)]` attribute
+/// with the integer type of the discriminant.
+#[proc_macro_derive(Binary, attributes(binary))]
+pub fn derive_binary(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match &input.data {
+        Data::Struct(data) => binary_struct(&input, &data.fields),
+        Data::Enum(data) => binary_enum(&input, data),
+        Data::Union(_) => Err(syn::Error::new(
+            input.span(),
+            "Binary cannot be derived for unions",
+        )),
+    }
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+/// Shows the fields in a grid, in declaration order.
+///
+/// Field attributes:
+/// - `#[inspector(read_only)]`: shown, but cannot be edited.
+/// - `#[inspector(tooltip = "...")]`: adds a "(?)" with the text next to the name.
+/// - `#[inspector(skip)]`: not shown.
+///
+/// Enums with only unit variants are shown as a combo box. Use
+/// `#[inspector(label = "...")]` on a variant to change its text.
+#[proc_macro_derive(Inspector, attributes(inspector))]
+pub fn derive_inspector(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match &input.data {
+        Data::Struct(data) => inspector_struct(&input, &data.fields),
+        Data::Enum(data) => inspector_enum(&input, data),
+        Data::Union(_) => Err(syn::Error::new(
+            input.span(),
+            "Inspector cannot be derived for unions",
+        )),
+    }
+    .unwrap_or_else(syn::Error::into_compile_error)
+    .into()
+}
+
+#[derive(Default)]
+struct BinaryField {
+    len: Option<Type>,
+    count: Option<Expr>,
+    pad: Option<Expr>,
+}
+
+impl BinaryField {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut options = Self::default();
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("binary")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("len") {
+                    options.len = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("count") {
+                    options.count = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("pad") {
+                    options.pad = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `len`, `count` or `pad`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        if options.len.is_some() && options.count.is_some() {
+            return Err(syn::Error::new(
+                field.span(),
+                "`len` and `count` cannot be used together",
+            ));
+        }
+
+        Ok(options)
+    }
+}
+
+fn named_fields(fields: &Fields) -> syn::Result<Vec<&syn::Field>> {
+    match fields {
+        Fields::Named(fields) => Ok(fields.named.iter().collect()),
+        _ => Err(syn::Error::new(
+            fields.span(),
+            "only structs with named fields are supported",
+        )),
+    }
+}
+
+fn binary_struct(input: &DeriveInput, fields: &Fields) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    let mut idents = Vec::new();
+
+    for field in named_fields(fields)? {
+        let ident = field.ident.as_ref().expect("named field");
+        let ty = &field.ty;
+        let label = ident.to_string();
+        let options = BinaryField::parse(field)?;
+
+        let elements = |len: TokenStream2| {
+            quote! {
+                let len = #len;
+                let mut elements = ::std::vec::Vec::with_capacity(len);
+                for i in 0..len {
+                    elements.push(
+                        crate::types::Binary::from_bytes(reader).index(i).field(#label)?,
+                    );
+                }
+                elements
+            }
+        };
+
+        if let Some(len_ty) = &options.len {
+            let read = elements(quote! {
+                <#len_ty as crate::types::Binary>::from_bytes(reader).field(#label)? as usize
+            });
+            reads.push(quote! { let #ident: #ty = { #read }; });
+            writes.push(quote! {
+                match <#len_ty>::try_from(self.#ident.len()) {
+                    Ok(len) => crate::types::Binary::to_bytes(&len, writer),
+                    Err(_) => {
+                        writer.fail(format!(
+                            "{} has {} elements, too many for a {} length",
+                            #label,
+                            self.#ident.len(),
+                            stringify!(#len_ty),
+                        ));
+                    }
+                }
+                for element in &self.#ident {
+                    crate::types::Binary::to_bytes(element, writer);
+                }
+            });
+        } else if let Some(count) = &options.count {
+            let read = elements(quote! { #count });
+            reads.push(quote! { let #ident: #ty = { #read }; });
+            writes.push(quote! {
+                if self.#ident.len() != #count {
+                    writer.fail(format!(
+                        "{} must have {} elements, not {}",
+                        #label,
+                        #count,
+                        self.#ident.len(),
+                    ));
+                }
+                for element in &self.#ident {
+                    crate::types::Binary::to_bytes(element, writer);
+                }
+            });
+        } else {
+            reads.push(quote! {
+                let #ident = <#ty as crate::types::Binary>::from_bytes(reader).field(#label)?;
+            });
+            writes.push(quote! {
+                crate::types::Binary::to_bytes(&self.#ident, writer);
+            });
+        }
+
+        if let Some(pad) = &options.pad {
+            reads.push(quote! { reader.read_bytes(#pad).field(#label)?; });
+            writes.push(quote! { writer.pad(#pad); });
+        }
+
+        idents.push(ident);
+    }
+
+    Ok(quote! {
+        impl #impl_generics crate::types::Binary for #name #ty_generics #where_clause {
+            fn from_bytes(
+                reader: &mut crate::buffer::BufferReader,
+            ) -> crate::ParseResult<Self>
+            where
+                Self: Sized,
+            {
+                use crate::error::ParseResultExt as _;
+                #(#reads)*
+                Ok(Self { #(#idents),* })
+            }
+
+            fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
+                #(#writes)*
+            }
+        }
+    })
+}
+
+fn unit_variants(data: &syn::DataEnum) -> syn::Result<Vec<&syn::Variant>> {
+    data.variants
+        .iter()
+        .map(|variant| match variant.fields {
+            Fields::Unit => Ok(variant),
+            _ => Err(syn::Error::new(
+                variant.span(),
+                "only enums with unit variants are supported",
+            )),
+        })
+        .collect()
+}
+
+fn binary_enum(input: &DeriveInput, data: &syn::DataEnum) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let label = name.to_string();
+
+    let mut repr = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("binary")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("repr") {
+                repr = Some(meta.value()?.parse::<Type>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `repr`"))
+            }
+        })?;
+    }
+    let repr = repr.ok_or_else(|| {
+        syn::Error::new(input.span(), "missing `#[binary(repr = ...)]` attribute")
+    })?;
+
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+
+    for variant in unit_variants(data)? {
+        let ident = &variant.ident;
+        let Some((_, discriminant)) = &variant.discriminant else {
+            return Err(syn::Error::new(
+                variant.span(),
+                "variants must have an explicit discriminant",
+            ));
+        };
+
+        reads.push(quote! { #discriminant => Ok(Self::#ident), });
+        writes.push(quote! { Self::#ident => #discriminant, });
+    }
+
+    Ok(quote! {
+        impl crate::types::Binary for #name {
+            fn from_bytes(
+                reader: &mut crate::buffer::BufferReader,
+            ) -> crate::ParseResult<Self>
+            where
+                Self: Sized,
+            {
+                let offset = reader.position();
+
+                match <#repr as crate::types::Binary>::from_bytes(reader)? {
+                    #(#reads)*
+                    value => Err(crate::error::ParseError::invalid_value(offset, #label, value)),
+                }
+            }
+
+            fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
+                let value: #repr = match self {
+                    #(#writes)*
+                };
+                crate::types::Binary::to_bytes(&value, writer);
+            }
+        }
+    })
+}
+
+fn inspector_struct(input: &DeriveInput, fields: &Fields) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut properties = Vec::new();
+
+    for field in named_fields(fields)? {
+        let ident: &Ident = field.ident.as_ref().expect("named field");
+        let label = ident.to_string();
+
+        let mut read_only = false;
+        let mut skip = false;
+        let mut tooltip: Option<LitStr> = None;
+
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("inspector"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("read_only") {
+                    read_only = true;
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                } else if meta.path.is_ident("tooltip") {
+                    tooltip = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected `read_only`, `skip` or `tooltip`"));
+                }
+                Ok(())
+            })?;
+        }
+
+        if skip {
+            continue;
+        }
+
+        properties.push(match (read_only, tooltip) {
+            (true, Some(_)) => {
+                return Err(syn::Error::new(
+                    field.span(),
+                    "`read_only` and `tooltip` cannot be used together",
+                ));
+            }
+            (true, None) => {
+                quote! { crate::editor::property_read_only(#label, &mut self.#ident, ui); }
+            }
+            (false, Some(tooltip)) => {
+                quote! { crate::editor::property_tooltip(#label, #tooltip, &mut self.#ident, ui); }
+            }
+            (false, None) => quote! { crate::editor::property(#label, &mut self.#ident, ui); },
+        });
+    }
+
+    Ok(quote! {
+        impl #impl_generics crate::editor::Inspector for #name #ty_generics #where_clause {
+            fn show(&mut self, ui: &mut eframe::egui::Ui) {
+                crate::editor::struct_ui(ui, |ui| {
+                    #(#properties)*
+                });
+            }
+        }
+    })
+}
+
+fn inspector_enum(input: &DeriveInput, data: &syn::DataEnum) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+
+    let mut labels = Vec::new();
+    let mut idents = Vec::new();
+
+    for variant in unit_variants(data)? {
+        let mut label = LitStr::new(&variant.ident.to_string(), variant.ident.span());
+
+        for attr in variant
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("inspector"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("label") {
+                    label = meta.value()?.parse()?;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `label`"))
+                }
+            })?;
+        }
+
+        labels.push(label);
+        idents.push(&variant.ident);
+    }
+
+    Ok(quote! {
+        impl crate::editor::Inspector for #name {
+            fn show(&mut self, ui: &mut eframe::egui::Ui) {
+                let selected_text = match self {
+                    #(Self::#idents => #labels,)*
+                };
+
+                // Every combo box needs its own id, there may be many of the
+                // same enum, e.g. in a list.
+                eframe::egui::ComboBox::from_id_salt(ui.next_auto_id())
+                    .selected_text(selected_text)
+                    .show_ui(ui, |ui| {
+                        #(ui.selectable_value(self, Self::#idents, #labels);)*
+                    });
+            }
+        }
+    })
+}
@@ -1,17 +1,34 @@
+use crate::{
+    ParseResult,
+    codepage::Codepage,
+    error::{ParseError, ParseErrorKind},
+};
+
 /// Helper struct for reading from a buffer of bytes.
 pub struct BufferReader<'a> {
     buffer: &'a [u8],
     position: usize,
+    codepage: Codepage,
 }
 
 impl<'a> BufferReader<'a> {
     pub fn new(buffer: &'a [u8]) -> Self {
+        Self::with_codepage(buffer, Codepage::default())
+    }
+
+    /// Strings will be decoded from `codepage`.
+    pub fn with_codepage(buffer: &'a [u8], codepage: Codepage) -> Self {
         Self {
             buffer,
             position: 0,
+            codepage,
         }
     }
 
+    pub fn codepage(&self) -> Codepage {
+        self.codepage
+    }
+
     pub fn len(&self) -> usize {
         self.buffer.len()
     }
@@ -21,59 +38,111 @@ impl<'a> BufferReader<'a> {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.position == self.buffer.len()
+        self.position >= self.buffer.len()
     }
 
-    pub fn skip(&mut self, count: usize) {
-        self.position += count;
+    pub fn skip(&mut self, count: usize) -> ParseResult<()> {
+        self.read(count, "bytes").map(|_| ())
     }
 
-    pub fn read_u8(&mut self) -> crate::Result<u8> {
-        let bytes = self.read_bytes(1)?;
-        Ok(u8::from_le_bytes(bytes.try_into()?))
+    pub fn read_u8(&mut self) -> ParseResult<u8> {
+        Ok(u8::from_le_bytes(self.read_array("u8")?))
     }
 
-    pub fn read_bool(&mut self) -> crate::Result<bool> {
-        Ok(self.read_u8()? != 0)
+    pub fn read_bool(&mut self) -> ParseResult<bool> {
+        Ok(u8::from_le_bytes(self.read_array("bool")?) != 0)
     }
 
-    pub fn read_i16(&mut self) -> crate::Result<i16> {
-        let bytes = self.read_bytes(2)?;
-        Ok(i16::from_le_bytes(bytes.try_into()?))
+    pub fn read_i16(&mut self) -> ParseResult<i16> {
+        Ok(i16::from_le_bytes(self.read_array("i16")?))
     }
 
-    pub fn read_u16(&mut self) -> crate::Result<u16> {
-        let bytes = self.read_bytes(2)?;
-        Ok(u16::from_le_bytes(bytes.try_into()?))
+    pub fn read_u16(&mut self) -> ParseResult<u16> {
+        Ok(u16::from_le_bytes(self.read_array("u16")?))
     }
 
-    pub fn read_u32(&mut self) -> crate::Result<u32> {
-        let bytes = self.read_bytes(4)?;
-        Ok(u32::from_le_bytes(bytes.try_into()?))
+    pub fn read_u32(&mut self) -> ParseResult<u32> {
+        Ok(u32::from_le_bytes(self.read_array("u32")?))
     }
 
-    pub fn read_i32(&mut self) -> crate::Result<i32> {
-        let bytes = self.read_bytes(4)?;
-        Ok(i32::from_le_bytes(bytes.try_into()?))
+    pub fn read_i32(&mut self) -> ParseResult<i32> {
+        Ok(i32::from_le_bytes(self.read_array("i32")?))
     }
 
-    pub fn read_f32(&mut self) -> crate::Result<f32> {
-        let bytes = self.read_bytes(4)?;
-        Ok(f32::from_le_bytes(bytes.try_into()?))
+    pub fn read_f32(&mut self) -> ParseResult<f32> {
+        Ok(f32::from_le_bytes(self.read_array("f32")?))
     }
 
-    pub fn read_string(&mut self) -> crate::Result<String> {
+    pub fn read_string(&mut self) -> ParseResult<String> {
+        let offset = self.position;
         let length = self.read_u32()? as usize;
-        let bytes = self.read_bytes(length)?;
-        Ok(String::from_utf8(bytes.to_vec())?)
+        let bytes = self.read(length, "String")?.to_vec();
+        self.decode(&bytes, offset, "String")
+    }
+
+    /// Decodes `bytes` read at `offset` from the codepage of the reader.
+    pub fn decode(
+        &self,
+        bytes: &[u8],
+        offset: usize,
+        expected: &'static str,
+    ) -> ParseResult<String> {
+        self.decode_with(bytes, offset, expected, self.codepage)
+    }
+
+    /// Decodes `bytes` read at `offset` from `codepage`.
+    pub fn decode_with(
+        &self,
+        bytes: &[u8],
+        offset: usize,
+        expected: &'static str,
+        codepage: Codepage,
+    ) -> ParseResult<String> {
+        codepage.decode(bytes).ok_or_else(|| {
+            ParseError::new(
+                ParseErrorKind::InvalidString(format!("not valid {codepage}")),
+                offset,
+                expected,
+            )
+        })
+    }
+
+    pub fn read_bytes(&mut self, count: usize) -> ParseResult<&[u8]> {
+        self.read(count, "bytes")
     }
 
-    pub fn read_bytes(&mut self, count: usize) -> crate::Result<&[u8]> {
-        if self.position + count > self.buffer.len() {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::UnexpectedEof,
-                format!("Not enough bytes in buffer (position {})", self.position),
-            )));
+    /// Fails with [`ParseErrorKind::TrailingBytes`] if not everything has been read.
+    pub fn expect_end(&self) -> ParseResult<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        Err(ParseError::new(
+            ParseErrorKind::TrailingBytes(self.buffer.len().saturating_sub(self.position)),
+            self.position,
+            "end of file",
+        ))
+    }
+
+    fn read_array<const N: usize>(&mut self, expected: &'static str) -> ParseResult<[u8; N]> {
+        Ok(self
+            .read(N, expected)?
+            .try_into()
+            .expect("slice must have N bytes"))
+    }
+
+    fn read(&mut self, count: usize, expected: &'static str) -> ParseResult<&[u8]> {
+        let remaining = self.buffer.len().saturating_sub(self.position);
+
+        if count > remaining {
+            return Err(ParseError::new(
+                ParseErrorKind::UnexpectedEof {
+                    needed: count,
+                    remaining,
+                },
+                self.position,
+                expected,
+            ));
         }
 
         let slice = &self.buffer[self.position..self.position + count];
@@ -83,19 +152,39 @@ impl<'a> BufferReader<'a> {
 }
 
 /// Helper struct for writing data to a buffer.
+#[derive(Default)]
 pub struct BufferWriter {
     buffer: Vec<u8>,
+    codepage: Codepage,
+    error: Option<String>,
 }
 
 impl BufferWriter {
     pub fn new() -> Self {
-        Self { buffer: Vec::new() }
+        Self::with_codepage(Codepage::default())
+    }
+
+    /// Strings will be encoded to `codepage`.
+    pub fn with_codepage(codepage: Codepage) -> Self {
+        Self {
+            buffer: Vec::new(),
+            codepage,
+            error: None,
+        }
+    }
+
+    pub fn codepage(&self) -> Codepage {
+        self.codepage
     }
 
     pub fn len(&self) -> usize {
         self.buffer.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
     pub fn write_u8(&mut self, value: u8) -> &mut Self {
         self.buffer.push(value);
         self
@@ -132,11 +221,21 @@ impl BufferWriter {
     }
 
     pub fn write_string(&mut self, value: &str) -> &mut Self {
-        self.write_u32(value.len() as u32);
-        self.write_bytes(value.as_bytes());
+        let bytes = self.encode(value, self.codepage);
+        self.write_u32(bytes.len() as u32);
+        self.write_bytes(&bytes);
         self
     }
 
+    /// Encodes `value` to `codepage`. Characters that the codepage cannot
+    /// represent are written as `?`, and make [`BufferWriter::finish`] fail.
+    pub fn encode(&mut self, value: &str, codepage: Codepage) -> Vec<u8> {
+        codepage.encode(value).unwrap_or_else(|| {
+            self.fail(format!("\"{value}\" cannot be written in {codepage}"));
+            codepage.encode_lossy(value)
+        })
+    }
+
     pub fn write_bytes(&mut self, bytes: &[u8]) -> &mut Self {
         self.buffer.extend_from_slice(bytes);
         self
@@ -149,7 +248,43 @@ impl BufferWriter {
         self
     }
 
-    pub fn finish(self) -> Vec<u8> {
-        self.buffer.to_vec()
+    /// Records that the data can't be written correctly, e.g. because a list
+    /// is too long for its length prefix. Only the first error is kept, and
+    /// [`BufferWriter::finish`] returns it.
+    pub fn fail(&mut self, message: impl Into<String>) -> &mut Self {
+        self.error.get_or_insert_with(|| message.into());
+        self
+    }
+
+    /// Writes everything written to `other`, including its error.
+    pub fn append(&mut self, other: BufferWriter) -> &mut Self {
+        if let Some(error) = other.error {
+            self.fail(error);
+        }
+        self.buffer.extend(other.buffer);
+        self
+    }
+
+    pub fn finish(self) -> crate::Result<Vec<u8>> {
+        match self.error {
+            Some(error) => Err(error.into()),
+            None => Ok(self.buffer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BufferReader;
+
+    #[test]
+    fn past_the_end() {
+        let bytes = [0; 4];
+        let mut reader = BufferReader::new(&bytes);
+        assert!(reader.skip(5).is_err());
+        assert!(reader.expect_end().is_err());
+
+        reader.skip(4).unwrap();
+        assert!(reader.expect_end().is_ok());
     }
 }
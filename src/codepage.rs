@@ -0,0 +1,167 @@
+//! Text encodings used by the game files.
+
+use encoding_rs::Encoding;
+
+/// Encoding of the strings in a file. English releases only use ASCII, so
+/// UTF-8 works for them, but localized releases use Windows codepages.
+/// Stored in JSON by the same name as on the command line, e.g. "windows-1251".
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(into = "&'static str", try_from = "String")]
+pub enum Codepage {
+    #[default]
+    Utf8,
+    /// Central European (Polish, Czech, ...).
+    Windows1250,
+    /// Cyrillic (Russian, ...).
+    Windows1251,
+    /// Western European (German, French, ...).
+    Windows1252,
+}
+
+impl Codepage {
+    pub const ALL: [Codepage; 4] = [
+        Codepage::Utf8,
+        Codepage::Windows1250,
+        Codepage::Windows1251,
+        Codepage::Windows1252,
+    ];
+
+    pub fn name(self) -> &'static str {
+        self.encoding().name()
+    }
+
+    pub fn is_utf8(&self) -> bool {
+        *self == Codepage::Utf8
+    }
+
+    /// Returns `None` if `bytes` are not valid in this codepage. Every byte is
+    /// valid in the Windows codepages, so decoding them never fails and
+    /// [`Codepage::encode`] restores the exact bytes.
+    pub fn decode(self, bytes: &[u8]) -> Option<String> {
+        self.encoding()
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(|s| s.into_owned())
+    }
+
+    /// Returns `None` if `s` contains characters that the codepage cannot
+    /// represent.
+    pub fn encode(self, s: &str) -> Option<Vec<u8>> {
+        let (bytes, _, had_errors) = self.encoding().encode(s);
+        (!had_errors).then(|| bytes.into_owned())
+    }
+
+    /// Characters that the codepage cannot represent are replaced with `?`.
+    pub fn encode_lossy(self, s: &str) -> Vec<u8> {
+        let encoding = self.encoding();
+        let (bytes, _, had_errors) = encoding.encode(s);
+
+        if !had_errors {
+            return bytes.into_owned();
+        }
+
+        // encoding_rs writes unmappable characters as HTML entities.
+        let mut bytes = Vec::with_capacity(s.len());
+        let mut buffer = [0; 4];
+        for c in s.chars() {
+            let (char_bytes, _, had_errors) = encoding.encode(c.encode_utf8(&mut buffer));
+            if had_errors {
+                bytes.push(b'?');
+            } else {
+                bytes.extend_from_slice(&char_bytes);
+            }
+        }
+        bytes
+    }
+
+    fn encoding(self) -> &'static Encoding {
+        match self {
+            Codepage::Utf8 => encoding_rs::UTF_8,
+            Codepage::Windows1250 => encoding_rs::WINDOWS_1250,
+            Codepage::Windows1251 => encoding_rs::WINDOWS_1251,
+            Codepage::Windows1252 => encoding_rs::WINDOWS_1252,
+        }
+    }
+}
+
+impl std::fmt::Display for Codepage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for Codepage {
+    type Err = String;
+
+    /// Accepts the encoding names, e.g. "windows-1251", also without the dash
+    /// as older JSON files have them, e.g. "Windows1251", and bare codepage
+    /// numbers, e.g. "1251".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalize = |name: &str| name.replace('-', "").to_ascii_lowercase();
+
+        Self::ALL
+            .into_iter()
+            .find(|codepage| {
+                normalize(codepage.name()) == normalize(s)
+                    || codepage.name().strip_prefix("windows-") == Some(s)
+            })
+            .ok_or_else(|| format!("Unknown codepage: {s}"))
+    }
+}
+
+impl From<Codepage> for &'static str {
+    fn from(codepage: Codepage) -> Self {
+        codepage.name()
+    }
+}
+
+impl TryFrom<String> for Codepage {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Codepage;
+
+    #[test]
+    fn round_trip() {
+        let bytes = (0..=255).collect::<Vec<u8>>();
+
+        for codepage in [
+            Codepage::Windows1250,
+            Codepage::Windows1251,
+            Codepage::Windows1252,
+        ] {
+            let s = codepage.decode(&bytes).unwrap();
+            assert_eq!(codepage.encode(&s).unwrap(), bytes, "{codepage}");
+        }
+
+        assert_eq!(Codepage::Windows1251.decode(&[0xcf, 0xf0]).unwrap(), "Пр");
+        assert!(Codepage::Windows1252.encode("aПb").is_none());
+        assert_eq!(Codepage::Windows1252.encode_lossy("aПb"), b"a?b");
+        assert!(Codepage::Utf8.decode(&[0xff]).is_none());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            serde_json::to_string(&Codepage::Windows1251).unwrap(),
+            "\"windows-1251\""
+        );
+
+        for name in ["windows-1251", "WINDOWS-1251", "1251", "Windows1251"] {
+            let json = format!("\"{name}\"");
+            assert_eq!(
+                serde_json::from_str::<Codepage>(&json).unwrap(),
+                Codepage::Windows1251,
+                "{name}"
+            );
+        }
+
+        assert_eq!("Utf8".parse(), Ok(Codepage::Utf8));
+        assert!("1253".parse::<Codepage>().is_err());
+    }
+}
@@ -1,8 +1,16 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use eframe::egui::{self, Align2, Checkbox, DragValue, Layout};
 
-use crate::{buffer::BufferWriter, types::Format};
+use crate::{
+    codepage::Codepage,
+    types::{
+        Format, FormatType, LoadOptions,
+        packed::{Archive, Modification, modify},
+    },
+};
+
+pub use divine_tools_derive::Inspector;
 
 const WINDOW_TITLE: &str = "Divine Tools";
 
@@ -20,7 +28,44 @@ pub fn run_editor() -> crate::Result<()> {
 #[derive(Default)]
 pub struct Editor {
     loaded_file: Option<Format>,
+    archive: Option<ArchiveBrowser>,
     message: Option<Message>,
+    /// Format and codepage picked in the "Open as" and "Codepage" menus.
+    load_options: LoadOptions,
+}
+
+/// Packed file shown in the archive panel.
+struct ArchiveBrowser {
+    path: PathBuf,
+    archive: Archive,
+    /// Only entries containing this text are listed.
+    filter: String,
+    /// Entry the loaded file was opened from, if any. Saving the loaded file
+    /// writes it back into the archive.
+    opened_entry: Option<String>,
+    /// Whether the format of each entry can be detected, see [`supported_entries`].
+    supported: Vec<bool>,
+}
+
+/// Checks the name and the start of every entry once, reading entries for
+/// every frame would be too slow.
+fn supported_entries(archive: &Archive) -> Vec<bool> {
+    archive
+        .entries()
+        .iter()
+        .map(|entry| {
+            let file_name = entry.path.rsplit('\\').next().unwrap_or_default();
+            archive
+                .read_entry_start(entry, FormatType::SNIFF_LEN)
+                .is_ok_and(|start| {
+                    Format::is_supported(
+                        Path::new(&file_name.to_lowercase()),
+                        &start,
+                        entry.size as usize,
+                    )
+                })
+        })
+        .collect()
 }
 
 impl Editor {
@@ -30,6 +75,108 @@ impl Editor {
             severity,
         });
     }
+
+    /// Replaces the data of an archive entry with `bytes`.
+    fn save_to_archive(&mut self, entry_path: &str, bytes: Vec<u8>) {
+        let browser = self.archive.as_mut().expect("archive must be open");
+
+        // The archive has to be closed while it is being rewritten. Its entries
+        // stay listed if it can't be opened again.
+        browser.archive.close();
+        let saved = modify(
+            &browser.path,
+            entry_path,
+            Modification::Replace { data: bytes },
+        );
+        let reopened = Archive::open(&browser.path).map(|archive| {
+            browser.supported = supported_entries(&archive);
+            browser.archive = archive;
+        });
+
+        let mut errors = Vec::new();
+        if let Err(e) = saved {
+            errors.push(format!("Failed to save file to the archive: {e}"));
+        }
+        if let Err(e) = reopened {
+            errors.push(format!("Failed to reopen the archive: {e}"));
+        }
+
+        if !errors.is_empty() {
+            self.show_message(&errors.join("\n"), MessageSeverity::Error);
+        }
+    }
+
+    fn show_archive_panel(&mut self, ctx: &egui::Context) {
+        let Some(browser) = &mut self.archive else {
+            return;
+        };
+
+        let load_options = self.load_options;
+        let mut entry_to_open = None;
+
+        egui::SidePanel::left("archive_panel")
+            .resizable(true)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                ui.heading(
+                    browser
+                        .path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy(),
+                );
+                ui.add(egui::TextEdit::singleline(&mut browser.filter).hint_text("Filter"));
+                ui.separator();
+
+                let filter = browser.filter.to_lowercase();
+                let entries = std::iter::zip(browser.archive.entries(), &browser.supported)
+                    .filter(|(entry, _)| entry.path.to_lowercase().contains(&filter))
+                    .collect::<Vec<_>>();
+
+                let row_height = ui.spacing().interact_size.y;
+
+                egui::ScrollArea::both().auto_shrink(false).show_rows(
+                    ui,
+                    row_height,
+                    entries.len(),
+                    |ui, range| {
+                        for &(entry, &supported) in &entries[range] {
+                            let supported = load_options.format_type.is_some() || supported;
+                            let selected = browser.opened_entry.as_ref() == Some(&entry.path);
+
+                            let response = ui
+                                .add_enabled(
+                                    supported,
+                                    egui::Button::selectable(selected, &entry.path),
+                                )
+                                .on_disabled_hover_text("Unsupported file format");
+
+                            if response.clicked() {
+                                entry_to_open = Some(entry.path.clone());
+                            }
+                        }
+                    },
+                );
+            });
+
+        let Some(entry_path) = entry_to_open else {
+            return;
+        };
+
+        match Format::from_archive_with(&browser.archive, &entry_path, load_options) {
+            Ok(v) => {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Title(format!(
+                    "{WINDOW_TITLE} - {} - {entry_path}",
+                    browser.path.display()
+                )));
+                browser.opened_entry = Some(entry_path);
+                self.loaded_file = Some(v);
+            }
+            Err(e) => {
+                self.show_message(&format!("Failed to load file: {e}"), MessageSeverity::Error);
+            }
+        }
+    }
 }
 
 struct Message {
@@ -80,13 +227,17 @@ impl eframe::App for Editor {
                         return;
                     };
 
-                    match Format::from_file(&file_path) {
+                    match Format::from_file_with(&file_path, self.load_options) {
                         Ok(v) => {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Title(format!(
                                 "{WINDOW_TITLE} - {}",
                                 file_path.display()
                             )));
-                            self.loaded_file = Some(v)
+                            self.loaded_file = Some(v);
+
+                            if let Some(browser) = &mut self.archive {
+                                browser.opened_entry = None;
+                            }
                         }
                         Err(e) => {
                             self.show_message(
@@ -97,6 +248,62 @@ impl eframe::App for Editor {
                     }
                 }
 
+                ui.menu_button("Open as", |ui| {
+                    let format_type = &mut self.load_options.format_type;
+                    ui.radio_value(format_type, None, "Detect automatically");
+                    ui.separator();
+                    for format_type in FormatType::ALL {
+                        ui.radio_value(
+                            &mut self.load_options.format_type,
+                            Some(format_type),
+                            format_type.to_string(),
+                        );
+                    }
+                });
+
+                ui.menu_button("Codepage", |ui| {
+                    for codepage in Codepage::ALL {
+                        ui.radio_value(
+                            &mut self.load_options.codepage,
+                            codepage,
+                            codepage.to_string(),
+                        );
+                    }
+                });
+
+                if ui.button("Open archive").clicked() {
+                    let file_dialog = rfd::FileDialog::new().add_filter("Packed file", &["cmp"]);
+
+                    let Some(file_path) = file_dialog.pick_file() else {
+                        return;
+                    };
+
+                    match Archive::open(&file_path) {
+                        Ok(archive) => {
+                            self.archive = Some(ArchiveBrowser {
+                                path: file_path,
+                                supported: supported_entries(&archive),
+                                archive,
+                                filter: String::new(),
+                                opened_entry: None,
+                            });
+                        }
+                        Err(e) => {
+                            self.show_message(
+                                &format!("Failed to open archive: {e}"),
+                                MessageSeverity::Error,
+                            );
+                        }
+                    }
+                }
+
+                if ui
+                    .add_enabled(self.archive.is_some(), egui::Button::new("Close archive"))
+                    .clicked()
+                {
+                    self.archive = None;
+                }
+
                 ui.separator();
 
                 ui.add_enabled_ui(self.loaded_file.is_some(), |ui| {
@@ -127,7 +334,7 @@ impl eframe::App for Editor {
                                 return;
                             };
 
-                            let Ok(serialized) = serde_json::to_string_pretty(&loaded_file) else {
+                            let Ok(serialized) = loaded_file.to_json() else {
                                 self.show_message(
                                     "Failed to serialize the file",
                                     MessageSeverity::Error,
@@ -145,9 +352,29 @@ impl eframe::App for Editor {
                         }
 
                         if save_clicked || save_as_clicked {
-                            let mut writer = BufferWriter::new();
-                            loaded_file.binary.to_bytes(&mut writer);
-                            let bytes = writer.finish();
+                            let bytes = match loaded_file.to_bytes() {
+                                Ok(bytes) => bytes,
+                                Err(e) => {
+                                    self.show_message(
+                                        &format!("Failed to save the file: {e}"),
+                                        MessageSeverity::Error,
+                                    );
+                                    return;
+                                }
+                            };
+
+                            let opened_entry = self
+                                .archive
+                                .as_ref()
+                                .and_then(|browser| browser.opened_entry.clone());
+
+                            if save_clicked
+                                && loaded_file.path.is_none()
+                                && let Some(entry_path) = opened_entry
+                            {
+                                self.save_to_archive(&entry_path, bytes);
+                                return;
+                            }
 
                             let path = if save_as_clicked {
                                 let file_dialog = rfd::FileDialog::new();
@@ -199,12 +426,22 @@ impl eframe::App for Editor {
                         }
                     };
 
-                    let Ok(deserialized) = serde_json::from_str::<Format>(&file) else {
-                        self.show_message("Failed to load file", MessageSeverity::Error);
-                        return;
+                    let deserialized = match Format::from_json(&file) {
+                        Ok(v) => v,
+                        Err(e) => {
+                            self.show_message(
+                                &format!("Failed to load file: {e}"),
+                                MessageSeverity::Error,
+                            );
+                            return;
+                        }
                     };
 
                     self.loaded_file = Some(deserialized);
+
+                    if let Some(browser) = &mut self.archive {
+                        browser.opened_entry = None;
+                    }
                 }
 
                 ui.separator();
@@ -215,6 +452,8 @@ impl eframe::App for Editor {
             });
         });
 
+        self.show_archive_panel(ctx);
+
         egui::CentralPanel::default().show(ctx, |ui| match &mut self.loaded_file {
             Some(file) => {
                 egui::ScrollArea::vertical()
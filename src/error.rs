@@ -0,0 +1,129 @@
+//! Errors produced while parsing binary files.
+
+use std::fmt::Display;
+
+pub type ParseResult<T> = std::result::Result<T, ParseError>;
+
+/// Parse failure with the location of the value that could not be read.
+#[derive(Debug)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Offset of the value from the start of the file.
+    pub offset: usize,
+    /// Type of the value, e.g. `u32` or `QuestStatus`.
+    pub expected: &'static str,
+    /// Fields and indices leading to the value, innermost first.
+    path: Vec<PathSegment>,
+}
+
+#[derive(Debug)]
+pub enum ParseErrorKind {
+    /// The file ends before the value does.
+    UnexpectedEof { needed: usize, remaining: usize },
+    /// The value was read, but is not valid for its type.
+    InvalidValue(String),
+    /// A string is not valid UTF-8 or is not null-terminated.
+    InvalidString(String),
+    /// The whole file was parsed, but there is data left after it.
+    TrailingBytes(usize),
+    /// The file does not have the layout of the format at all.
+    Corrupted(String),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PathSegment {
+    Field(&'static str),
+    Index(usize),
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, offset: usize, expected: &'static str) -> Self {
+        Self {
+            kind,
+            offset,
+            expected,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn invalid_value(offset: usize, expected: &'static str, value: impl Display) -> Self {
+        Self::new(
+            ParseErrorKind::InvalidValue(value.to_string()),
+            offset,
+            expected,
+        )
+    }
+
+    /// Path to the value, e.g. `regions[3].unknown0[2][1].volume`.
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+
+        for segment in self.path.iter().rev() {
+            match segment {
+                PathSegment::Field(name) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(name);
+                }
+                PathSegment::Index(index) => path.push_str(&format!("[{index}]")),
+            }
+        }
+
+        path
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnexpectedEof { needed, remaining } => write!(
+                f,
+                "Unexpected end of file (needed {needed} bytes, {remaining} left)"
+            )?,
+            ParseErrorKind::InvalidValue(value) => write!(f, "Invalid value {value}")?,
+            ParseErrorKind::InvalidString(error) => write!(f, "Invalid string ({error})")?,
+            ParseErrorKind::TrailingBytes(count) => {
+                write!(f, "{count} bytes left after the end of the file")?
+            }
+            ParseErrorKind::Corrupted(message) => write!(f, "{message}")?,
+        }
+
+        write!(
+            f,
+            " at offset {} while reading {}",
+            self.offset, self.expected
+        )?;
+
+        if !self.path.is_empty() {
+            write!(f, " ({})", self.path())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Adds the location of a value to parse errors as they are returned from
+/// nested `from_bytes` calls.
+pub trait ParseResultExt {
+    fn field(self, name: &'static str) -> Self;
+    fn index(self, index: usize) -> Self;
+}
+
+impl<T> ParseResultExt for ParseResult<T> {
+    fn field(self, name: &'static str) -> Self {
+        self.map_err(|mut error| {
+            error.path.push(PathSegment::Field(name));
+            error
+        })
+    }
+
+    fn index(self, index: usize) -> Self {
+        self.map_err(|mut error| {
+            error.path.push(PathSegment::Index(index));
+            error
+        })
+    }
+}
@@ -0,0 +1,179 @@
+//! Reading and writing PNG images.
+
+use std::{fs::File, io::BufWriter, path::Path};
+
+/// 8-bit grayscale image, stored row by row.
+pub struct GrayImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl GrayImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    pub fn save(&self, path: &Path) -> crate::Result<()> {
+        save(
+            path,
+            self.width,
+            self.height,
+            png::ColorType::Grayscale,
+            &self.pixels,
+        )
+    }
+
+    /// Colour images are converted to grayscale, transparent pixels become black.
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let (width, height, pixels) = load_rgba(path)?;
+
+        Ok(Self {
+            width,
+            height,
+            pixels: pixels
+                .into_iter()
+                .map(|[r, g, b, a]| (luma(r, g, b) * a as u32 / 255) as u8)
+                .collect(),
+        })
+    }
+}
+
+/// 8-bit RGB image, stored row by row.
+pub struct RgbImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl RgbImage {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![[0; 3]; width * height],
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> crate::Result<()> {
+        save(
+            path,
+            self.width,
+            self.height,
+            png::ColorType::Rgb,
+            self.pixels.as_flattened(),
+        )
+    }
+
+    /// Transparent pixels become black.
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let (width, height, pixels) = load_rgba(path)?;
+
+        Ok(Self {
+            width,
+            height,
+            pixels: pixels
+                .into_iter()
+                .map(|[r, g, b, a]| [r, g, b].map(|c| (c as u32 * a as u32 / 255) as u8))
+                .collect(),
+        })
+    }
+
+    /// Scales the image to the given size, averaging the pixels that end up in
+    /// the same place.
+    pub fn resize(&self, width: usize, height: usize) -> Self {
+        let mut image = Self::new(width, height);
+
+        if self.width == 0 || self.height == 0 {
+            return image;
+        }
+
+        for y in 0..height {
+            let top = y * self.height / height;
+            let bottom = ((y + 1) * self.height / height).max(top + 1);
+
+            for x in 0..width {
+                let left = x * self.width / width;
+                let right = ((x + 1) * self.width / width).max(left + 1);
+
+                let mut sum = [0u32; 3];
+                for source_y in top..bottom {
+                    for source_x in left..right {
+                        let pixel = self.pixels[source_y * self.width + source_x];
+                        for (sum, channel) in sum.iter_mut().zip(pixel) {
+                            *sum += channel as u32;
+                        }
+                    }
+                }
+
+                let count = ((bottom - top) * (right - left)) as u32;
+                image.pixels[y * width + x] = sum.map(|sum| (sum / count) as u8);
+            }
+        }
+
+        image
+    }
+}
+
+fn save(
+    path: &Path,
+    width: usize,
+    height: usize,
+    color_type: png::ColorType,
+    data: &[u8],
+) -> crate::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(color_type);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Loads any kind of PNG image as 8-bit RGBA.
+fn load_rgba(path: &Path) -> crate::Result<(usize, usize, Vec<[u8; 4]>)> {
+    let file = std::io::BufReader::new(File::open(path)?);
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size().ok_or("image is too large")?];
+    let info = reader.next_frame(&mut buffer)?;
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    let channels = info.color_type.samples();
+    let mut pixels = Vec::with_capacity(width * height);
+
+    for line in buffer.chunks(info.line_size).take(height) {
+        for pixel in line.chunks(channels).take(width) {
+            pixels.push(match *pixel {
+                [luma] => [luma, luma, luma, 255],
+                [luma, alpha] => [luma, luma, luma, alpha],
+                [r, g, b] => [r, g, b, 255],
+                [r, g, b, alpha] => [r, g, b, alpha],
+                _ => unreachable!("8-bit images have at most 4 channels"),
+            });
+        }
+    }
+
+    Ok((width, height, pixels))
+}
+
+fn luma(r: u8, g: u8, b: u8) -> u32 {
+    (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000
+}
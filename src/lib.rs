@@ -0,0 +1,12 @@
+//! Tools for inspecting and modifying various binary files from Divine Divinity and Beyond Divinity.
+
+pub mod buffer;
+pub mod codepage;
+pub mod editor;
+pub mod error;
+pub mod image;
+pub mod roundtrip;
+pub mod types;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+pub use error::ParseResult;
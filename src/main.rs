@@ -2,13 +2,16 @@ use std::path::PathBuf;
 
 use argh::FromArgs;
 
-use crate::editor::run_editor;
-
-mod buffer;
-mod editor;
-mod types;
-
-type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+use divine_tools::{
+    Result,
+    codepage::Codepage,
+    editor::run_editor,
+    types::{
+        Format, FormatType, LoadOptions, font, packed,
+        quickinfo::{self, PixelFormat},
+        shroud,
+    },
+};
 
 /// Tool for inspecting and modifying various binary files from Divine Divinity and Beyond Divinity.
 #[derive(FromArgs)]
@@ -22,6 +25,15 @@ struct Args {
 enum SubCommand {
     Pack(PackCommand),
     Unpack(UnpackCommand),
+    List(ListCommand),
+    Cmp(CmpCommand),
+    Verify(VerifyCommand),
+    Dump(DumpCommand),
+    Build(BuildCommand),
+    Roundtrip(RoundtripCommand),
+    Font(FontCommand),
+    QuickInfo(QuickInfoCommand),
+    Shroud(ShroudCommand),
 }
 
 /// unpacks a .cmp file
@@ -31,11 +43,289 @@ struct UnpackCommand {
     /// path to the .cmp file
     #[argh(positional)]
     path: PathBuf,
+    /// paths of files inside the .cmp file to extract (default: all files)
+    #[argh(positional)]
+    files: Vec<String>,
+    /// only extract files matching this glob pattern (can be repeated)
+    #[argh(option, short = 'i')]
+    include: Vec<String>,
+    /// do not extract files matching this glob pattern (can be repeated)
+    #[argh(option, short = 'e')]
+    exclude: Vec<String>,
+    /// directory to extract files to (default: current directory)
+    #[argh(option)]
+    output_dir: Option<PathBuf>,
     /// automatically confirm unpacking
     #[argh(switch, short = 'y')]
     assume_yes: bool,
 }
 
+/// lists files embedded in a .cmp file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "list")]
+struct ListCommand {
+    /// path to the .cmp file
+    #[argh(positional)]
+    path: PathBuf,
+    /// print the list as JSON
+    #[argh(switch)]
+    json: bool,
+}
+
+/// converts a binary file to JSON
+#[derive(FromArgs)]
+#[argh(subcommand, name = "dump")]
+struct DumpCommand {
+    /// path to the binary file
+    #[argh(positional)]
+    path: PathBuf,
+    /// output file (default: print to stdout)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// format of the file, e.g. "QuestLog" (default: detect from name and contents)
+    #[argh(option)]
+    format: Option<FormatType>,
+    /// codepage of the strings, e.g. "windows-1251" (default: utf-8)
+    #[argh(option, default = "Codepage::default()")]
+    codepage: Codepage,
+}
+
+/// converts a JSON file produced by `dump` back to a binary file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "build")]
+struct BuildCommand {
+    /// path to the JSON file
+    #[argh(positional)]
+    path: PathBuf,
+    /// output file (default: the JSON file path without the .json extension)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+/// checks that every supported file in a directory is saved without changes
+#[derive(FromArgs)]
+#[argh(subcommand, name = "roundtrip")]
+struct RoundtripCommand {
+    /// path to the game directory
+    #[argh(positional)]
+    path: PathBuf,
+    /// codepage of the strings, e.g. "windows-1251" (default: utf-8)
+    #[argh(option, default = "Codepage::default()")]
+    codepage: Codepage,
+}
+
+/// converts fonts to and from PNG glyph atlases
+#[derive(FromArgs)]
+#[argh(subcommand, name = "font")]
+struct FontCommand {
+    #[argh(subcommand)]
+    command: FontSubCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum FontSubCommand {
+    Export(FontExportCommand),
+    Import(FontImportCommand),
+}
+
+/// writes the glyphs of a .fnt file to a PNG atlas and a JSON metrics file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "export")]
+struct FontExportCommand {
+    /// path to the .fnt file
+    #[argh(positional)]
+    path: PathBuf,
+    /// output PNG file, the metrics are written next to it with a .json extension
+    /// (default: the .fnt file path with a .png extension)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// codepage of the characters, e.g. "windows-1251" (default: utf-8)
+    #[argh(option, default = "Codepage::default()")]
+    codepage: Codepage,
+}
+
+/// rebuilds a .fnt file from the JSON metrics and PNG atlas written by `font export`
+#[derive(FromArgs)]
+#[argh(subcommand, name = "import")]
+struct FontImportCommand {
+    /// path to the JSON metrics file written by `font export`
+    #[argh(positional)]
+    path: PathBuf,
+    /// output file (default: the JSON file path with a .fnt extension)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// codepage of the characters, e.g. "windows-1251" (default: utf-8)
+    #[argh(option, default = "Codepage::default()")]
+    codepage: Codepage,
+    /// overwrite the output file if it exists
+    #[argh(switch, short = 'y')]
+    assume_yes: bool,
+}
+
+/// converts save thumbnails to and from PNG images
+#[derive(FromArgs)]
+#[argh(subcommand, name = "quickinfo")]
+struct QuickInfoCommand {
+    #[argh(subcommand)]
+    command: QuickInfoSubCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum QuickInfoSubCommand {
+    Export(QuickInfoExportCommand),
+    Import(QuickInfoImportCommand),
+}
+
+/// writes the thumbnail of a quickinfo.000 file to a PNG image
+#[derive(FromArgs)]
+#[argh(subcommand, name = "export")]
+struct QuickInfoExportCommand {
+    /// path to the quickinfo.000 file
+    #[argh(positional)]
+    path: PathBuf,
+    /// output PNG file (default: thumbnail.png next to the quickinfo.000 file)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// pixel format of the thumbnail, "rgb565" or "rgb555" (default: rgb565)
+    #[argh(option, default = "PixelFormat::default()")]
+    pixel_format: PixelFormat,
+    /// codepage of the strings, e.g. "windows-1251" (default: utf-8)
+    #[argh(option, default = "Codepage::default()")]
+    codepage: Codepage,
+}
+
+/// replaces the thumbnail of a quickinfo.000 file with a PNG image, resizing it to fit
+#[derive(FromArgs)]
+#[argh(subcommand, name = "import")]
+struct QuickInfoImportCommand {
+    /// path to the quickinfo.000 file
+    #[argh(positional)]
+    path: PathBuf,
+    /// path to the PNG image
+    #[argh(positional)]
+    image: PathBuf,
+    /// output file (default: overwrite the quickinfo.000 file)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// pixel format of the thumbnail, "rgb565" or "rgb555" (default: rgb565)
+    #[argh(option, default = "PixelFormat::default()")]
+    pixel_format: PixelFormat,
+    /// codepage of the strings, e.g. "windows-1251" (default: utf-8)
+    #[argh(option, default = "Codepage::default()")]
+    codepage: Codepage,
+}
+
+/// converts fog of war files to and from PNG images
+#[derive(FromArgs)]
+#[argh(subcommand, name = "shroud")]
+struct ShroudCommand {
+    #[argh(subcommand)]
+    command: ShroudSubCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum ShroudSubCommand {
+    Export(ShroudExportCommand),
+    Import(ShroudImportCommand),
+}
+
+/// writes the cells of a shroud.xN file to a grayscale PNG image
+#[derive(FromArgs)]
+#[argh(subcommand, name = "export")]
+struct ShroudExportCommand {
+    /// path to the shroud.xN file
+    #[argh(positional)]
+    path: PathBuf,
+    /// output PNG file (default: the shroud file path with .png appended)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+/// replaces the cells of a shroud.xN file with a grayscale PNG image
+#[derive(FromArgs)]
+#[argh(subcommand, name = "import")]
+struct ShroudImportCommand {
+    /// path to the shroud.xN file
+    #[argh(positional)]
+    path: PathBuf,
+    /// path to the PNG image
+    #[argh(positional)]
+    image: PathBuf,
+    /// output file (default: overwrite the shroud file)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+/// checks a .cmp file for damage
+#[derive(FromArgs)]
+#[argh(subcommand, name = "verify")]
+struct VerifyCommand {
+    /// path to the .cmp file
+    #[argh(positional)]
+    path: PathBuf,
+}
+
+/// modifies single files inside a .cmp file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "cmp")]
+struct CmpCommand {
+    #[argh(subcommand)]
+    command: CmpSubCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum CmpSubCommand {
+    Add(CmpAddCommand),
+    Replace(CmpReplaceCommand),
+    Remove(CmpRemoveCommand),
+}
+
+/// adds a file to a .cmp file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "add")]
+struct CmpAddCommand {
+    /// path to the .cmp file
+    #[argh(positional)]
+    archive: PathBuf,
+    /// path of the file inside the .cmp file
+    #[argh(positional)]
+    entry_path: String,
+    /// file to add
+    #[argh(positional)]
+    file: PathBuf,
+}
+
+/// replaces a file inside a .cmp file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "replace")]
+struct CmpReplaceCommand {
+    /// path to the .cmp file
+    #[argh(positional)]
+    archive: PathBuf,
+    /// path of the file inside the .cmp file
+    #[argh(positional)]
+    entry_path: String,
+    /// file to replace it with
+    #[argh(positional)]
+    file: PathBuf,
+}
+
+/// removes a file from a .cmp file
+#[derive(FromArgs)]
+#[argh(subcommand, name = "remove")]
+struct CmpRemoveCommand {
+    /// path to the .cmp file
+    #[argh(positional)]
+    archive: PathBuf,
+    /// path of the file inside the .cmp file
+    #[argh(positional)]
+    entry_path: String,
+}
+
 /// packs files into a .cmp file
 #[derive(FromArgs)]
 #[argh(subcommand, name = "pack")]
@@ -43,9 +333,23 @@ struct PackCommand {
     /// output file (default: packed.cmp)
     #[argh(option, short = 'o')]
     output: Option<PathBuf>,
+    /// directory containing files to pack (default: current directory)
+    #[argh(option)]
+    input_dir: Option<PathBuf>,
 }
 
-fn main() -> Result<()> {
+fn main() -> std::process::ExitCode {
+    // Errors are printed with Display, returning them from main would use Debug.
+    match run() {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<()> {
     let args = argh::from_env::<Args>();
 
     let Some(command) = args.command else {
@@ -54,11 +358,153 @@ fn main() -> Result<()> {
 
     let current_dir = std::env::current_dir()?;
     match command {
-        SubCommand::Pack(pack) => {
-            crate::types::packed::pack(&current_dir, &pack.output.unwrap_or("packed.cmp".into()))
-        }
+        SubCommand::Pack(pack) => packed::pack(
+            &pack.input_dir.unwrap_or(current_dir),
+            &pack.output.unwrap_or("packed.cmp".into()),
+        ),
         SubCommand::Unpack(unpack) => {
-            crate::types::packed::unpack(&unpack.path, &current_dir, unpack.assume_yes)
+            let filter = packed::Filter::new(&unpack.include, &unpack.exclude, &unpack.files)?;
+            packed::unpack(
+                &unpack.path,
+                &unpack.output_dir.unwrap_or(current_dir),
+                &filter,
+                unpack.assume_yes,
+            )
+        }
+        SubCommand::List(list) => packed::list(&list.path, list.json),
+        SubCommand::Dump(dump) => {
+            let options = LoadOptions {
+                format_type: dump.format,
+                codepage: dump.codepage,
+            };
+            let json = Format::from_file_with(&dump.path, options)
+                .map_err(|e| format!("Failed to load {}: {e}", dump.path.display()))?
+                .to_json()?;
+
+            match dump.output {
+                Some(output) => std::fs::write(output, json)?,
+                None => println!("{json}"),
+            }
+
+            Ok(())
+        }
+        SubCommand::Build(build) => {
+            let output = match build.output {
+                Some(output) => output,
+                None if build.path.extension().is_some_and(|e| e == "json") => {
+                    build.path.with_extension("")
+                }
+                None => return Err("Output file must be specified with -o".into()),
+            };
+
+            let json = std::fs::read_to_string(&build.path)
+                .map_err(|e| format!("Failed to open {}: {e}", build.path.display()))?;
+            let format = Format::from_json(&json)
+                .map_err(|e| format!("Failed to parse {}: {e}", build.path.display()))?;
+
+            std::fs::write(output, format.to_bytes()?)?;
+            Ok(())
+        }
+        SubCommand::Roundtrip(roundtrip) => {
+            divine_tools::roundtrip::roundtrip(&roundtrip.path, roundtrip.codepage)
+        }
+        SubCommand::Font(font) => match font.command {
+            FontSubCommand::Export(export) => {
+                let output = export
+                    .output
+                    .unwrap_or_else(|| export.path.with_extension("png"));
+                font::export(&export.path, &output, export.codepage)
+                    .map_err(|e| format!("Failed to export {}: {e}", export.path.display()).into())
+            }
+            FontSubCommand::Import(import) => {
+                let output = import
+                    .output
+                    .unwrap_or_else(|| import.path.with_extension("fnt"));
+
+                // The default output is the font that was exported.
+                if output.exists() && !import.assume_yes {
+                    return Err(format!(
+                        "{} already exists, use -o to choose another file or -y to overwrite it",
+                        output.display()
+                    )
+                    .into());
+                }
+
+                font::import(&import.path, &output, import.codepage)
+                    .map_err(|e| format!("Failed to import {}: {e}", import.path.display()).into())
+            }
+        },
+        SubCommand::QuickInfo(quick_info) => match quick_info.command {
+            QuickInfoSubCommand::Export(export) => {
+                let output = export
+                    .output
+                    .unwrap_or_else(|| export.path.with_file_name("thumbnail.png"));
+                quickinfo::export_thumbnail(
+                    &export.path,
+                    &output,
+                    export.pixel_format,
+                    export.codepage,
+                )
+                .map_err(|e| format!("Failed to export {}: {e}", export.path.display()).into())
+            }
+            QuickInfoSubCommand::Import(import) => {
+                let output = import.output.as_ref().unwrap_or(&import.path);
+                quickinfo::import_thumbnail(
+                    &import.path,
+                    &import.image,
+                    output,
+                    import.pixel_format,
+                    import.codepage,
+                )
+                .map_err(|e| format!("Failed to import {}: {e}", import.image.display()).into())
+            }
+        },
+        SubCommand::Shroud(shroud) => match shroud.command {
+            ShroudSubCommand::Export(export) => {
+                let output = export.output.unwrap_or_else(|| {
+                    let mut path = export.path.clone().into_os_string();
+                    path.push(".png");
+                    path.into()
+                });
+                shroud::export(&export.path, &output)
+                    .map_err(|e| format!("Failed to export {}: {e}", export.path.display()).into())
+            }
+            ShroudSubCommand::Import(import) => {
+                let output = import.output.as_ref().unwrap_or(&import.path);
+                shroud::import(&import.path, &import.image, output)
+                    .map_err(|e| format!("Failed to import {}: {e}", import.image.display()).into())
+            }
+        },
+        SubCommand::Verify(verify) => packed::verify(&verify.path),
+        SubCommand::Cmp(cmp) => {
+            use packed::{Modification, modify};
+
+            match cmp.command {
+                CmpSubCommand::Add(add) => {
+                    println!("Adding {}", add.entry_path);
+                    modify(
+                        &add.archive,
+                        &add.entry_path,
+                        Modification::Add {
+                            data: std::fs::read(&add.file)?,
+                        },
+                    )
+                }
+                CmpSubCommand::Replace(replace) => {
+                    println!("Replacing {}", replace.entry_path);
+                    modify(
+                        &replace.archive,
+                        &replace.entry_path,
+                        Modification::Replace {
+                            data: std::fs::read(&replace.file)?,
+                        },
+                    )
+                }
+                CmpSubCommand::Remove(remove) => {
+                    println!("Removing {}", remove.entry_path);
+                    modify(&remove.archive, &remove.entry_path, Modification::Remove)
+                }
+            }
         }
     }
 }
@@ -0,0 +1,110 @@
+//! Checks that every supported file in a directory is saved exactly as it was loaded.
+
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    codepage::Codepage,
+    types::{Format, FormatType, LoadOptions},
+};
+
+/// Loads and saves every supported file in `directory` (recursively) and
+/// reports files whose saved bytes differ from the original ones. Strings are
+/// decoded from `codepage`.
+pub fn roundtrip(directory: &Path, codepage: Codepage) -> crate::Result<()> {
+    let mut files = Vec::new();
+    let mut stack = vec![directory.to_path_buf()];
+
+    while let Some(parent) = stack.pop() {
+        if parent.is_dir() {
+            let mut dir = std::fs::read_dir(&parent)?.collect::<Result<Vec<_>, _>>()?;
+
+            dir.sort_unstable_by_key(|v| v.path().to_string_lossy().to_lowercase());
+
+            for entry in dir.iter().rev() {
+                stack.push(entry.path());
+            }
+        } else if parent.is_file() && is_supported(&parent)? {
+            files.push(parent);
+        }
+    }
+
+    let mut failed = 0;
+    let mut skipped = 0;
+    let options = LoadOptions {
+        codepage,
+        ..Default::default()
+    };
+
+    for path in &files {
+        let display_path = path.strip_prefix(directory).unwrap_or(path).display();
+
+        let result = match Format::from_file_with(path, options) {
+            Ok(format) => check_file(path, &format),
+            // Many files happen to have a size that fits some format, so files
+            // that were only detected by their contents may not be game files.
+            Err(e) if FormatType::from_file_name(path).is_none() => {
+                println!("SKIP  {display_path}: {e}");
+                skipped += 1;
+                continue;
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => println!("OK    {display_path}"),
+            Err(e) => {
+                println!("FAIL  {display_path}: {e}");
+                failed += 1;
+            }
+        }
+    }
+
+    println!(
+        "{} files checked, {failed} failed, {skipped} skipped",
+        files.len() - skipped
+    );
+
+    if failed > 0 {
+        return Err("Some files are not saved identically".into());
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if the format of the file can be detected.
+fn is_supported(path: &Path) -> crate::Result<bool> {
+    let mut start = Vec::with_capacity(FormatType::SNIFF_LEN);
+    File::open(path)?
+        .take(FormatType::SNIFF_LEN as u64)
+        .read_to_end(&mut start)?;
+
+    Ok(Format::is_supported(
+        path,
+        &start,
+        path.metadata()?.len() as usize,
+    ))
+}
+
+fn check_file(path: &PathBuf, format: &Format) -> crate::Result<()> {
+    let input = std::fs::read(path)?;
+    let output = format.to_bytes()?;
+
+    if let Some(offset) = std::iter::zip(&input, &output).position(|(a, b)| a != b) {
+        return Err(format!("first difference at offset {offset}").into());
+    }
+
+    if input.len() != output.len() {
+        return Err(format!(
+            "input is {} bytes long, output is {} bytes long",
+            input.len(),
+            output.len()
+        )
+        .into());
+    }
+
+    Ok(())
+}
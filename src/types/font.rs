@@ -1,97 +1,545 @@
 //! .fnt
 
+use std::path::{Path, PathBuf};
+
+use eframe::egui::{self, Color32, Rect, Sense, Stroke, StrokeKind, vec2};
+
 use crate::{
-    editor::{Inspector, property, struct_ui},
-    types::{Binary, FixedArray},
+    codepage::Codepage,
+    editor::{Inspector, property, property_read_only, struct_ui},
+    error::{ParseError, ParseErrorKind, ParseResultExt},
+    image::GrayImage,
+    types::{Binary, FixedArray, read_file, write_file},
 };
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct Font {
     unknown0: FixedArray<u8, 18>,
-    glyph_count: u32,
+    /// One entry per character code. Written back as is, so it may need
+    /// to be updated by hand when glyphs are added.
+    character_table: FixedArray<u32, 256>,
     glyphs: Vec<GlyphData>,
 }
 
 #[derive(Default, serde::Serialize, serde::Deserialize)]
-struct GlyphData {
-    unknown0: FixedArray<u8, 20>,
+pub struct GlyphData {
+    /// See [`GlyphData::size`].
+    header: FixedArray<u8, 20>,
     character: char,
-    buffer_length: u32,
+    unknown0: u8,
     buffer: Vec<u8>,
 }
 
+impl GlyphData {
+    /// Returns the width and height of the glyph bitmap.
+    ///
+    /// The glyph header is not fully understood. Its first two `u32`s are
+    /// assumed to be the size of the bitmap, stored in the buffer with one
+    /// byte of coverage per pixel, row by row. This hasn't been checked
+    /// against the game files yet, so the header is only ever read, never
+    /// written. Returns `None` if the size doesn't match the length of the
+    /// buffer.
+    pub fn size(&self) -> Option<(usize, usize)> {
+        let header = &self.header.elements;
+        let width = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+        let height = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+
+        (width.checked_mul(height) == Some(self.buffer.len())).then_some((width, height))
+    }
+}
+
 impl Binary for Font {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
-        let unknown0 = <FixedArray<u8, 18>>::from_bytes(reader)?;
-        let glyph_count = u32::from_bytes(reader)?;
-        reader.skip(1024);
+        let unknown0 = <FixedArray<u8, 18>>::from_bytes(reader).field("unknown0")?;
+        let glyph_count = u32::from_bytes(reader).field("glyph_count")?;
+        let character_table =
+            <FixedArray<u32, 256>>::from_bytes(reader).field("character_table")?;
         let mut glyphs = Vec::new();
 
         for _ in 0..glyph_count {
-            glyphs.push(GlyphData::from_bytes(reader)?);
+            glyphs.push(
+                GlyphData::from_bytes(reader)
+                    .index(glyphs.len())
+                    .field("glyphs")?,
+            );
         }
 
         Ok(Self {
             unknown0,
-            glyph_count,
+            character_table,
             glyphs,
         })
     }
 
-    fn to_bytes(&self, _writer: &mut crate::buffer::BufferWriter) {
-        todo!()
+    fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
+        self.unknown0.to_bytes(writer);
+        (self.glyphs.len() as u32).to_bytes(writer);
+        self.character_table.to_bytes(writer);
+
+        for glyph in &self.glyphs {
+            glyph.to_bytes(writer);
+        }
     }
 }
 
 impl Binary for GlyphData {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
-        let unknown0 = <FixedArray<u8, 20>>::from_bytes(reader)?;
-        let character = u8::from_bytes(reader)? as char;
-        reader.skip(1);
-        let buffer_length = u32::from_bytes(reader)?;
-        let buffer = Vec::new();
-
-        // -4 because length number itself is included in the buffer length.
-        reader.skip((buffer_length - 4) as usize);
+        let header = <FixedArray<u8, 20>>::from_bytes(reader).field("header")?;
+        let character = decode_character(
+            u8::from_bytes(reader).field("character")?,
+            reader.codepage(),
+        );
+        let unknown0 = u8::from_bytes(reader).field("unknown0")?;
+
+        let offset = reader.position();
+        let buffer_length = u32::from_bytes(reader).field("buffer_length")?;
+
+        // The length number itself is included in the buffer length.
+        let Some(length) = buffer_length.checked_sub(4) else {
+            return Err(ParseError::invalid_value(
+                offset,
+                "buffer length",
+                buffer_length,
+            ))
+            .field("buffer_length");
+        };
+        let buffer = reader.read_bytes(length as usize).field("buffer")?.to_vec();
+
         // For some reason the buffer is null-terminated.
-        reader.skip(1);
+        let offset = reader.position();
+        let terminator = u8::from_bytes(reader).field("buffer")?;
+        if terminator != 0 {
+            return Err(ParseError::new(
+                ParseErrorKind::InvalidValue(terminator.to_string()),
+                offset,
+                "null terminator",
+            ))
+            .field("buffer");
+        }
 
         Ok(GlyphData {
-            unknown0,
+            header,
             character,
-            buffer_length,
+            unknown0,
             buffer,
         })
     }
 
-    fn to_bytes(&self, _writer: &mut crate::buffer::BufferWriter) {
-        todo!()
+    fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
+        self.header.to_bytes(writer);
+        encode_character(self.character, writer).to_bytes(writer);
+        self.unknown0.to_bytes(writer);
+        (self.buffer.len() as u32 + 4).to_bytes(writer);
+        writer.write_bytes(&self.buffer);
+        writer.write_u8(0);
     }
 }
 
-impl Inspector for GlyphData {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
-        struct_ui(ui, |ui| {
-            property("unknown0", &mut self.unknown0, ui);
-            property("character", &mut self.character, ui);
-            property("buffer_length", &mut self.buffer_length, ui);
-            property("buffer", &mut self.buffer, ui);
+/// Characters are single bytes in the codepage of the font. Bytes that aren't
+/// valid on their own in UTF-8 are taken as Latin-1, like before codepages
+/// were supported.
+fn decode_character(byte: u8, codepage: Codepage) -> char {
+    codepage
+        .decode(&[byte])
+        .and_then(|s| s.chars().next())
+        .unwrap_or(byte as char)
+}
+
+fn encode_character(character: char, writer: &mut crate::buffer::BufferWriter) -> u8 {
+    let codepage = writer.codepage();
+
+    if let Some([byte]) = codepage
+        .encode(character.encode_utf8(&mut [0; 4]))
+        .as_deref()
+    {
+        return *byte;
+    }
+
+    if codepage.is_utf8()
+        && let Ok(byte) = u8::try_from(character)
+    {
+        return byte;
+    }
+
+    writer.fail(format!("{character:?} cannot be written in {codepage}"));
+    b'?'
+}
+
+/// Number of glyphs per row in an exported atlas.
+const ATLAS_COLUMNS: usize = 16;
+
+/// Everything besides the glyph bitmaps that is needed to rebuild a font from
+/// an atlas.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Metrics {
+    /// File name of the atlas, in the same directory as the metrics.
+    atlas: PathBuf,
+    unknown0: FixedArray<u8, 18>,
+    character_table: FixedArray<u32, 256>,
+    glyphs: Vec<GlyphMetrics>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GlyphMetrics {
+    character: char,
+    /// Position of the bitmap in the atlas.
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    /// Written back as is, so the size of a glyph can't be changed.
+    header: FixedArray<u8, 20>,
+    unknown0: u8,
+    /// Buffer of a glyph without a known bitmap layout, written back as is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    buffer: Option<Vec<u8>>,
+}
+
+/// Writes the glyphs of the font at `path` to a grayscale PNG atlas at `output`,
+/// and the metrics needed by [`import`] next to it with a .json extension.
+pub fn export(path: &Path, output: &Path, codepage: Codepage) -> crate::Result<()> {
+    let font: Font = read_file(path, codepage)?;
+
+    // Every glyph gets a cell of the same size, with a pixel of spacing.
+    let sizes = font.glyphs.iter().filter_map(GlyphData::size);
+    let cell_width = sizes.clone().map(|(width, _)| width).max().unwrap_or(0) + 1;
+    let cell_height = sizes.map(|(_, height)| height).max().unwrap_or(0) + 1;
+    let rows = font.glyphs.len().div_ceil(ATLAS_COLUMNS).max(1);
+
+    let mut atlas = GrayImage::new(ATLAS_COLUMNS * cell_width, rows * cell_height);
+    let mut glyphs = Vec::with_capacity(font.glyphs.len());
+
+    for (i, glyph) in font.glyphs.into_iter().enumerate() {
+        let x = i % ATLAS_COLUMNS * cell_width;
+        let y = i / ATLAS_COLUMNS * cell_height;
+        let size = glyph.size();
+        let (width, height) = size.unwrap_or((0, 0));
+
+        for (row, line) in glyph.buffer.chunks(width.max(1)).take(height).enumerate() {
+            for (column, &value) in line.iter().enumerate() {
+                atlas.set(x + column, y + row, value);
+            }
+        }
+
+        glyphs.push(GlyphMetrics {
+            character: glyph.character,
+            x,
+            y,
+            width,
+            height,
+            header: glyph.header,
+            unknown0: glyph.unknown0,
+            buffer: size.is_none().then_some(glyph.buffer),
         });
     }
+
+    let metrics = Metrics {
+        atlas: output.file_name().ok_or("Output must be a file")?.into(),
+        unknown0: font.unknown0,
+        character_table: font.character_table,
+        glyphs,
+    };
+
+    atlas.save(output)?;
+    std::fs::write(
+        output.with_extension("json"),
+        serde_json::to_string_pretty(&metrics)?,
+    )?;
+    Ok(())
+}
+
+/// Rebuilds a font from the metrics at `path` written by [`export`] and the
+/// atlas they name.
+pub fn import(path: &Path, output: &Path, codepage: Codepage) -> crate::Result<()> {
+    let metrics: Metrics = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let atlas = GrayImage::load(&path.with_file_name(&metrics.atlas))?;
+
+    let mut glyphs = Vec::with_capacity(metrics.glyphs.len());
+
+    for glyph in metrics.glyphs {
+        let data = match glyph.buffer {
+            Some(buffer) => GlyphData {
+                header: glyph.header,
+                character: glyph.character,
+                unknown0: glyph.unknown0,
+                buffer,
+            },
+            None => {
+                let right = glyph.x.checked_add(glyph.width);
+                let bottom = glyph.y.checked_add(glyph.height);
+
+                let (Some(right), Some(bottom)) = (right, bottom) else {
+                    return Err(
+                        format!("Glyph '{}' has an invalid position", glyph.character).into(),
+                    );
+                };
+
+                if right > atlas.width || bottom > atlas.height {
+                    return Err(format!(
+                        "Glyph '{}' is outside of the {}x{} atlas",
+                        glyph.character, atlas.width, atlas.height
+                    )
+                    .into());
+                }
+
+                let mut pixels = Vec::with_capacity(glyph.width * glyph.height);
+                for y in glyph.y..bottom {
+                    for x in glyph.x..right {
+                        pixels.push(atlas.get(x, y));
+                    }
+                }
+
+                let data = GlyphData {
+                    header: glyph.header,
+                    character: glyph.character,
+                    unknown0: glyph.unknown0,
+                    buffer: pixels,
+                };
+
+                if data.size() != Some((glyph.width, glyph.height)) {
+                    return Err(format!(
+                        "Glyph '{}' must keep the size from its header",
+                        glyph.character
+                    )
+                    .into());
+                }
+
+                data
+            }
+        };
+
+        glyphs.push(data);
+    }
+
+    let font = Font {
+        unknown0: metrics.unknown0,
+        character_table: metrics.character_table,
+        glyphs,
+    };
+
+    write_file(output, &font, codepage)
+}
+
+/// Text shown in the preview until it is changed.
+const SAMPLE_TEXT: &str = "The quick brown fox jumps over the lazy dog.";
+
+/// Size of a glyph pixel on screen, shared by the preview and all glyphs.
+fn zoom_id() -> egui::Id {
+    egui::Id::new("font_zoom")
+}
+
+impl Font {
+    fn show_preview(&self, ui: &mut egui::Ui) {
+        let text_id = ui.id().with("sample_text");
+        let mut text = ui
+            .data(|d| d.get_temp::<String>(text_id))
+            .unwrap_or_else(|| SAMPLE_TEXT.to_owned());
+        let mut zoom = ui.data(|d| d.get_temp(zoom_id())).unwrap_or(4.0);
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut text);
+            ui.add(egui::Slider::new(&mut zoom, 1.0..=16.0).text("zoom"));
+        });
+
+        let line_height = self
+            .glyphs
+            .iter()
+            .filter_map(GlyphData::size)
+            .map(|(_, height)| height)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        // Characters without a glyph (or with an unknown layout) are shown as
+        // boxes of this width.
+        let missing_width = line_height.div_ceil(2);
+
+        let characters = text
+            .chars()
+            .map(|c| {
+                let glyph = self.glyphs.iter().find(|glyph| glyph.character == c);
+                (c, glyph.and_then(|glyph| Some((glyph, glyph.size()?))))
+            })
+            .collect::<Vec<_>>();
+
+        let width = characters
+            .iter()
+            .map(|(_, glyph)| glyph.map_or(missing_width, |(_, (width, _))| width) + 1)
+            .sum::<usize>();
+
+        let (rect, _) = ui.allocate_exact_size(
+            vec2(width as f32, line_height as f32) * zoom,
+            Sense::hover(),
+        );
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::BLACK);
+
+        // The glyphs are aligned at the top, as their offsets aren't known.
+        let mut x = 0;
+        for (c, glyph) in characters {
+            let origin = rect.min + vec2(x as f32, 0.0) * zoom;
+
+            match glyph {
+                Some((glyph, (width, _))) => {
+                    paint_bitmap(&painter, origin, zoom, width, &glyph.buffer);
+                    x += width + 1;
+                }
+                None => {
+                    if c != ' ' {
+                        let size = vec2(missing_width as f32, line_height as f32) * zoom;
+                        painter.rect_stroke(
+                            Rect::from_min_size(origin, size),
+                            0.0,
+                            Stroke::new(1.0, Color32::RED),
+                            StrokeKind::Inside,
+                        );
+                    }
+                    x += missing_width + 1;
+                }
+            }
+        }
+
+        ui.data_mut(|d| {
+            d.insert_temp(text_id, text);
+            d.insert_temp(zoom_id(), zoom);
+        });
+    }
+}
+
+impl GlyphData {
+    /// Shows the bitmap as a grid. Clicking a pixel toggles it, dragging
+    /// paints the value chosen by the first pixel.
+    fn show_bitmap(&mut self, ui: &mut egui::Ui) {
+        let Some((width, height)) = self.size() else {
+            ui.label(format!("Unknown layout ({} bytes)", self.buffer.len()));
+            return;
+        };
+
+        ui.label(format!("{width}x{height}"));
+
+        let zoom = ui
+            .data(|d| d.get_temp(zoom_id()))
+            .unwrap_or(4.0f32)
+            .max(4.0);
+        let (rect, response) = ui.allocate_exact_size(
+            vec2(width as f32, height as f32) * zoom,
+            Sense::click_and_drag(),
+        );
+
+        if let Some(position) = response.interact_pointer_pos() {
+            let cell = ((position - rect.min) / zoom).floor();
+
+            if (0.0..width as f32).contains(&cell.x) && (0.0..height as f32).contains(&cell.y) {
+                let index = cell.y as usize * width + cell.x as usize;
+                let paint_id = response.id.with("paint_value");
+
+                let value = if ui.input(|i| i.pointer.any_pressed()) {
+                    let value = if self.buffer[index] > 127 { 0 } else { 255 };
+                    ui.data_mut(|d| d.insert_temp(paint_id, value));
+                    value
+                } else {
+                    ui.data(|d| d.get_temp(paint_id)).unwrap_or(255)
+                };
+
+                self.buffer[index] = value;
+            }
+        }
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::BLACK);
+        paint_bitmap(&painter, rect.min, zoom, width, &self.buffer);
+
+        let stroke = Stroke::new(1.0, Color32::from_gray(40));
+        for x in 1..width {
+            let x = rect.min.x + x as f32 * zoom;
+            painter.vline(x, rect.y_range(), stroke);
+        }
+        for y in 1..height {
+            let y = rect.min.y + y as f32 * zoom;
+            painter.hline(rect.x_range(), y, stroke);
+        }
+    }
+}
+
+fn paint_bitmap(
+    painter: &egui::Painter,
+    origin: egui::Pos2,
+    zoom: f32,
+    width: usize,
+    pixels: &[u8],
+) {
+    for (i, &value) in pixels.iter().enumerate().filter(|(_, value)| **value != 0) {
+        let position = vec2((i % width) as f32, (i / width) as f32) * zoom;
+        painter.rect_filled(
+            Rect::from_min_size(origin + position, vec2(zoom, zoom)),
+            0.0,
+            Color32::from_gray(value),
+        );
+    }
 }
 
 impl Inspector for Font {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
+    fn show(&mut self, ui: &mut egui::Ui) {
         struct_ui(ui, |ui| {
             property("unknown0", &mut self.unknown0, ui);
-            property("glyph_count", &mut self.glyph_count, ui);
+            property("character_table", &mut self.character_table, ui);
+
+            ui.label("preview");
+            ui.vertical(|ui| self.show_preview(ui));
+            ui.end_row();
+
             property("glyphs", &mut self.glyphs, ui);
         });
     }
 }
+
+impl Inspector for GlyphData {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        struct_ui(ui, |ui| {
+            // Read-only until the layout is known, see `GlyphData::size`.
+            property_read_only("header", &mut self.header, ui);
+            property("character", &mut self.character, ui);
+            property("unknown0", &mut self.unknown0, ui);
+
+            ui.label("bitmap");
+            ui.vertical(|ui| self.show_bitmap(ui));
+            ui.end_row();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Font;
+    use crate::{
+        buffer::{BufferReader, BufferWriter},
+        types::Binary,
+    };
+
+    #[test]
+    fn font_glyphs() {
+        // Synthetic font following the assumed header layout, see `GlyphData::size`.
+        let mut bytes = vec![0; 18];
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend([7; 1024]);
+        // Glyph header: 2x3 bitmap.
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend([0; 12]);
+        bytes.extend([b'A', 1]);
+        bytes.extend(10u32.to_le_bytes());
+        bytes.extend([0, 255, 128, 0, 64, 32, 0]);
+
+        let font = Font::from_bytes(&mut BufferReader::new(&bytes)).unwrap();
+        assert_eq!(font.glyphs[0].size(), Some((2, 3)));
+
+        let mut writer = BufferWriter::new();
+        font.to_bytes(&mut writer);
+        assert_eq!(writer.finish().unwrap(), bytes);
+    }
+}
@@ -1,20 +1,21 @@
 use std::{
     any::Any,
-    ffi::{CStr, CString},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use eframe::egui;
 
 use crate::{
     buffer::{BufferReader, BufferWriter},
+    codepage::Codepage,
     editor::Inspector,
+    error::{ParseError, ParseErrorKind, ParseResultExt},
     types::{
         eggs::Eggs, font::Font, info::Info, magic::Magic, music::Music, objects_000::Objects000,
-        osiris_names::OsirisNames, osiris_objects::OsirisObjects, persist::Persist, props::Props,
-        quest_log::QuestLog, quickinfo::QuickInfo, reverbs::Reverbs, shroud::Shroud,
-        sound::SoundConfig, status_plate::StatusPlate, telpstates::TelpStates, text::Text,
-        usernotes::Notes, world::World,
+        osiris_names::OsirisNames, osiris_objects::OsirisObjects, packed::Archive,
+        persist::Persist, props::Props, quest_log::QuestLog, quickinfo::QuickInfo,
+        reverbs::Reverbs, shroud::Shroud, sound::SoundConfig, status_plate::StatusPlate,
+        telpstates::TelpStates, text::Text, usernotes::Notes, world::World,
     },
 };
 
@@ -41,10 +42,12 @@ pub mod text;
 pub mod usernotes;
 pub mod world;
 
-type FromBytesFn = fn(&mut BufferReader) -> crate::Result<Box<dyn Binary>>;
+pub use divine_tools_derive::Binary;
+
+type FromBytesFn = fn(&mut BufferReader) -> crate::ParseResult<Box<dyn Binary>>;
 
 pub trait Binary: erased_serde::Serialize + Inspector + Any {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized;
 
@@ -53,10 +56,28 @@ pub trait Binary: erased_serde::Serialize + Inspector + Any {
 
 erased_serde::serialize_trait_object!(Binary);
 
-pub fn from_bytes_dyn<T: Binary>(reader: &mut BufferReader) -> crate::Result<Box<dyn Binary>> {
+pub fn from_bytes_dyn<T: Binary>(reader: &mut BufferReader) -> crate::ParseResult<Box<dyn Binary>> {
     T::from_bytes(reader).map(|v| Box::new(v) as Box<dyn Binary>)
 }
 
+/// Reads a whole file as `T`, for when the format is already known.
+pub fn read_file<T: Binary>(path: &Path, codepage: Codepage) -> crate::Result<T> {
+    let bytes = std::fs::read(path)?;
+    let mut reader = BufferReader::with_codepage(&bytes, codepage);
+    let binary = T::from_bytes(&mut reader)?;
+    reader.expect_end()?;
+
+    Ok(binary)
+}
+
+pub fn write_file<T: Binary>(path: &Path, binary: &T, codepage: Codepage) -> crate::Result<()> {
+    let mut writer = BufferWriter::with_codepage(codepage);
+    binary.to_bytes(&mut writer);
+    std::fs::write(path, writer.finish()?)?;
+
+    Ok(())
+}
+
 #[derive(serde::Serialize)]
 pub struct Format {
     #[serde(skip)]
@@ -65,6 +86,17 @@ pub struct Format {
     pub file_name: Option<PathBuf>,
     format_type: FormatType,
     pub binary: Box<dyn Binary>,
+    #[serde(skip_serializing_if = "Codepage::is_utf8")]
+    pub codepage: Codepage,
+}
+
+/// Options for loading a file. The defaults detect the format and decode
+/// strings as UTF-8.
+#[derive(Clone, Copy, Default)]
+pub struct LoadOptions {
+    /// Skips format detection.
+    pub format_type: Option<FormatType>,
+    pub codepage: Codepage,
 }
 
 impl<'de> serde::Deserialize<'de> for Format {
@@ -118,11 +150,19 @@ impl<'de> serde::Deserialize<'de> for Format {
                     FormatType::World => Box::new(map.next_value::<World>()?),
                 };
 
+                // Missing in files exported before codepages were supported.
+                let codepage = match map.next_key::<&str>()? {
+                    Some("codepage") => map.next_value::<Codepage>()?,
+                    Some(key) => return Err(serde::de::Error::unknown_field(key, &["codepage"])),
+                    None => Codepage::default(),
+                };
+
                 Ok(Format {
                     path: None,
                     file_name: None,
                     format_type,
                     binary,
+                    codepage,
                 })
             }
         }
@@ -133,70 +173,112 @@ impl<'de> serde::Deserialize<'de> for Format {
 
 impl Format {
     pub fn from_file(path: &PathBuf) -> crate::Result<Self> {
+        Self::from_file_with(path, LoadOptions::default())
+    }
+
+    pub fn from_file_with(path: &PathBuf, options: LoadOptions) -> crate::Result<Self> {
+        let file = std::fs::read(path)?;
+        let mut format = Self::from_bytes(&file, path, options)?;
+        format.path = Some(path.to_owned());
+        Ok(format)
+    }
+
+    /// Loads a file embedded in a packed file. See [`Archive::entry`] for how
+    /// `path` is matched.
+    pub fn from_archive(archive: &Archive, path: &str) -> crate::Result<Self> {
+        Self::from_archive_with(archive, path, LoadOptions::default())
+    }
+
+    pub fn from_archive_with(
+        archive: &Archive,
+        path: &str,
+        options: LoadOptions,
+    ) -> crate::Result<Self> {
+        let entry = archive
+            .entry(path)
+            .ok_or_else(|| format!("File not found in the archive: {path}"))?;
+        let file = archive.read_entry(entry)?;
+
+        // File names are matched in lowercase, but casing inside archives varies.
+        let file_name = entry.path.rsplit('\\').next().unwrap_or_default();
+        Self::from_bytes(&file, Path::new(&file_name.to_lowercase()), options)
+    }
+
+    /// Serializes the file to JSON, the same way "Export as JSON" does in the editor.
+    pub fn to_json(&self) -> crate::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Deserializes a file exported with [`Format::to_json`].
+    pub fn from_json(json: &str) -> crate::Result<Self> {
+        // serde_json errors include the line and column.
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serializes the file back to its binary form.
+    /// Fails if the data can't be represented in the file, e.g. when a list
+    /// edited in JSON has the wrong number of elements.
+    pub fn to_bytes(&self) -> crate::Result<Vec<u8>> {
+        let mut writer = BufferWriter::with_codepage(self.codepage);
+        self.binary.to_bytes(&mut writer);
+        writer.finish()
+    }
+
+    /// Returns `true` if the format of a file can be determined from its path
+    /// or its contents, the same way [`FormatType::detect`] does. Only the
+    /// first [`FormatType::SNIFF_LEN`] bytes of the file are needed in `start`,
+    /// `len` is the size of the whole file.
+    pub fn is_supported(path: &Path, start: &[u8], len: usize) -> bool {
+        FormatType::from_file_name(path).is_some()
+            || FormatType::ALL
+                .into_iter()
+                .any(|format_type| format_type.sniff(start, len) > 0)
+    }
+
+    /// Parses `bytes` as `options.format_type`, or as the first of the detected
+    /// candidates (see [`FormatType::detect`]) that loads without errors.
+    fn from_bytes(bytes: &[u8], path: &Path, options: LoadOptions) -> crate::Result<Self> {
+        let candidates = match options.format_type {
+            Some(format_type) => vec![format_type],
+            None => FormatType::detect(path, bytes),
+        };
         let file_name = path
             .file_name()
-            .expect("must have file name")
-            .to_string_lossy()
-            .to_string();
-
-        let (format_type, load): (FormatType, FromBytesFn) = match file_name.as_str() {
-            "music.dat" => (FormatType::Music, from_bytes_dyn::<Music>),
-            "sound.cfg" => (FormatType::SoundConfig, from_bytes_dyn::<SoundConfig>),
-            "props.000" => (FormatType::Props, from_bytes_dyn::<Props>),
-            "magic.cmp" => (FormatType::Magic, from_bytes_dyn::<Magic>),
-            "statuspl.cmp" => (FormatType::StatusPlate, from_bytes_dyn::<StatusPlate>),
-            "usernotes.bin" | "mapflags.000" => (FormatType::Notes, from_bytes_dyn::<Notes>),
-            "eggs.000" => (FormatType::Eggs, from_bytes_dyn::<Eggs>),
-            "reverbs.dat" => (FormatType::Reverbs, from_bytes_dyn::<Reverbs>),
-            "osiobjects.000" => (FormatType::OsirisObjects, from_bytes_dyn::<OsirisObjects>),
-            "osinames.000" => (FormatType::OsirisNames, from_bytes_dyn::<OsirisNames>),
-            "persist.dat" => (FormatType::Persist, from_bytes_dyn::<Persist>),
-            "objects.000" => (FormatType::Objects000, from_bytes_dyn::<Objects000>),
-            "telpstates.000" => (FormatType::TelpStates, from_bytes_dyn::<TelpStates>),
-            "quest_log.000" => (FormatType::QuestLog, from_bytes_dyn::<QuestLog>),
-            "quickinfo.000" => (FormatType::QuickInfo, from_bytes_dyn::<QuickInfo>),
-            "text.cmp" => (FormatType::Text, from_bytes_dyn::<Text>),
-            "info.000" => (FormatType::Info, from_bytes_dyn::<Info>),
-            _ => {
-                let Some(extension) = path.extension() else {
-                    return Err("Unknown file format".into());
-                };
-
-                if extension == "fnt" {
-                    (FormatType::Font, from_bytes_dyn::<Font>)
-                } else if let Some(stem) = path.file_stem() {
-                    if stem == "shroud" {
-                        (FormatType::Shroud, from_bytes_dyn::<Shroud>)
-                    } else if stem == "world" {
-                        (FormatType::World, from_bytes_dyn::<World>)
-                    } else {
-                        return Err("Unknown file format".into());
-                    }
-                } else {
-                    return Err("Unknown file format".into());
+            .ok_or_else(|| format!("Not a file name: {}", path.display()))?;
+
+        let mut errors = Vec::new();
+
+        for format_type in candidates {
+            match format_type.load(bytes, options.codepage) {
+                Ok(binary) => {
+                    return Ok(Self {
+                        path: None,
+                        file_name: Some(PathBuf::from(file_name)),
+                        format_type,
+                        binary,
+                        codepage: options.codepage,
+                    });
                 }
+                Err(error) => errors.push((format_type, error)),
             }
-        };
-
-        let file = std::fs::read(path)?;
-        let mut reader = BufferReader::new(&file);
-        let binary = load(&mut reader)?;
-
-        if !reader.is_empty() {
-            return Err(format!("buffer is not empty: {}", reader.position()).into());
         }
 
-        Ok(Self {
-            path: Some(path.to_owned()),
-            file_name: Some(PathBuf::from(file_name)),
-            format_type,
-            binary,
-        })
+        match errors.len() {
+            0 => Err("Unknown file format".into()),
+            1 => Err(errors.remove(0).1.into()),
+            _ => {
+                let errors = errors
+                    .iter()
+                    .map(|(format_type, error)| format!("{format_type}: {error}"))
+                    .collect::<Vec<_>>();
+                Err(format!("Unknown file format (tried {})", errors.join("; ")).into())
+            }
+        }
     }
 }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-enum FormatType {
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum FormatType {
     Music,
     SoundConfig,
     Props,
@@ -219,48 +301,305 @@ enum FormatType {
     World,
 }
 
+impl FormatType {
+    pub const ALL: [FormatType; 20] = [
+        FormatType::Music,
+        FormatType::SoundConfig,
+        FormatType::Props,
+        FormatType::Magic,
+        FormatType::StatusPlate,
+        FormatType::Notes,
+        FormatType::Eggs,
+        FormatType::Reverbs,
+        FormatType::OsirisObjects,
+        FormatType::OsirisNames,
+        FormatType::Persist,
+        FormatType::Objects000,
+        FormatType::TelpStates,
+        FormatType::Font,
+        FormatType::QuestLog,
+        FormatType::QuickInfo,
+        FormatType::Text,
+        FormatType::Info,
+        FormatType::Shroud,
+        FormatType::World,
+    ];
+
+    /// Number of bytes at the start of a file that are looked at to detect its
+    /// format.
+    pub const SNIFF_LEN: usize = 6;
+
+    /// Returns the formats `bytes` may be in, most likely first. A file name
+    /// known to the game outweighs any of the content checks.
+    pub fn detect(path: &Path, bytes: &[u8]) -> Vec<FormatType> {
+        let from_file_name = Self::from_file_name(path);
+
+        let mut candidates = Self::ALL
+            .into_iter()
+            .map(|format_type| {
+                let name_score = if from_file_name == Some(format_type) {
+                    1000
+                } else {
+                    0
+                };
+                (
+                    format_type,
+                    name_score + format_type.sniff(bytes, bytes.len()),
+                )
+            })
+            .filter(|(_, score)| *score > 0)
+            .collect::<Vec<_>>();
+
+        // Stable, so ties keep the order of `ALL`.
+        candidates.sort_by(|(_, a), (_, b)| b.cmp(a));
+        candidates
+            .into_iter()
+            .map(|(format_type, _)| format_type)
+            .collect()
+    }
+
+    /// Determines the format of a file from the file names used by the game.
+    pub(crate) fn from_file_name(path: &Path) -> Option<FormatType> {
+        let file_name = path.file_name()?.to_string_lossy().to_string();
+
+        let format_type = match file_name.as_str() {
+            "music.dat" => FormatType::Music,
+            "sound.cfg" => FormatType::SoundConfig,
+            "props.000" => FormatType::Props,
+            "magic.cmp" => FormatType::Magic,
+            "statuspl.cmp" => FormatType::StatusPlate,
+            "usernotes.bin" | "mapflags.000" => FormatType::Notes,
+            "eggs.000" => FormatType::Eggs,
+            "reverbs.dat" => FormatType::Reverbs,
+            "osiobjects.000" => FormatType::OsirisObjects,
+            "osinames.000" => FormatType::OsirisNames,
+            "persist.dat" => FormatType::Persist,
+            "objects.000" => FormatType::Objects000,
+            "telpstates.000" => FormatType::TelpStates,
+            "quest_log.000" => FormatType::QuestLog,
+            "quickinfo.000" => FormatType::QuickInfo,
+            "text.cmp" => FormatType::Text,
+            "info.000" => FormatType::Info,
+            _ => {
+                if path.extension()? == "fnt" {
+                    FormatType::Font
+                } else if path.file_stem()? == "shroud" {
+                    FormatType::Shroud
+                } else if path.file_stem()? == "world" {
+                    FormatType::World
+                } else {
+                    return None;
+                }
+            }
+        };
+
+        Some(format_type)
+    }
+
+    /// Scores how well a file of `len` bytes starting with `start` matches the
+    /// layout of the format. Formats without a recognizable header or size
+    /// always score 0.
+    fn sniff(self, start: &[u8], len: usize) -> u32 {
+        let count = start
+            .get(..4)
+            .map(|count| u32::from_le_bytes(count.try_into().unwrap()) as usize);
+
+        // A `u32` element count followed by fixed-size records and `trailer` bytes.
+        let records = |size: usize, trailer: usize| {
+            count
+                .and_then(|count| count.checked_mul(size))
+                .and_then(|records| records.checked_add(4 + trailer))
+                == Some(len)
+        };
+
+        match self {
+            FormatType::QuestLog
+                if start.starts_with(b"ML3ID\0") && (len - 6).is_multiple_of(34) =>
+            {
+                100
+            }
+            FormatType::Shroud if len == 525826 => 100,
+            // The first row starts right after the 1024 row offsets.
+            FormatType::World if count == Some(4096) => 50,
+            FormatType::Magic if records(28, 0) => 40,
+            FormatType::Eggs if records(92, 0) => 40,
+            FormatType::Persist if records(32, 0) => 40,
+            FormatType::TelpStates if records(8, 0) => 40,
+            FormatType::OsirisNames if records(36, 0) => 40,
+            FormatType::OsirisObjects if records(8, 4) => 40,
+            FormatType::StatusPlate if len == 360 => 20,
+            FormatType::Info | FormatType::SoundConfig if len == 32 => 10,
+            FormatType::Objects000 if len > 0 && len.is_multiple_of(148) => 10,
+            _ => 0,
+        }
+    }
+
+    /// Parses `bytes`, which must be consumed entirely.
+    fn load(self, bytes: &[u8], codepage: Codepage) -> crate::ParseResult<Box<dyn Binary>> {
+        let load: FromBytesFn = match self {
+            FormatType::Music => from_bytes_dyn::<Music>,
+            FormatType::SoundConfig => from_bytes_dyn::<SoundConfig>,
+            FormatType::Props => from_bytes_dyn::<Props>,
+            FormatType::Magic => from_bytes_dyn::<Magic>,
+            FormatType::StatusPlate => from_bytes_dyn::<StatusPlate>,
+            FormatType::Notes => from_bytes_dyn::<Notes>,
+            FormatType::Eggs => from_bytes_dyn::<Eggs>,
+            FormatType::Reverbs => from_bytes_dyn::<Reverbs>,
+            FormatType::OsirisObjects => from_bytes_dyn::<OsirisObjects>,
+            FormatType::OsirisNames => from_bytes_dyn::<OsirisNames>,
+            FormatType::Persist => from_bytes_dyn::<Persist>,
+            FormatType::Objects000 => from_bytes_dyn::<Objects000>,
+            FormatType::TelpStates => from_bytes_dyn::<TelpStates>,
+            FormatType::Font => from_bytes_dyn::<Font>,
+            FormatType::QuestLog => from_bytes_dyn::<QuestLog>,
+            FormatType::QuickInfo => from_bytes_dyn::<QuickInfo>,
+            FormatType::Text => from_bytes_dyn::<Text>,
+            FormatType::Info => from_bytes_dyn::<Info>,
+            FormatType::Shroud => from_bytes_dyn::<Shroud>,
+            FormatType::World => from_bytes_dyn::<World>,
+        };
+
+        let mut reader = BufferReader::with_codepage(bytes, codepage);
+        let binary = load(&mut reader)?;
+        reader.expect_end()?;
+
+        Ok(binary)
+    }
+}
+
+impl std::fmt::Display for FormatType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::str::FromStr for FormatType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|format_type| format_type.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown format: {s}"))
+    }
+}
+
 #[derive(Default, serde::Serialize, serde::Deserialize)]
 pub struct FixedArray<T: Binary, const N: usize> {
     elements: Vec<T>,
 }
 
-/// Null-terminated CString with a fixed length.
-#[derive(Default, serde::Serialize, serde::Deserialize)]
-pub struct FixedCString<const N: usize> {
-    inner: CString,
+/// Codepage for strings that aren't valid in the codepage of the file. Every
+/// byte is valid in it.
+const FALLBACK_CODEPAGE: Codepage = Codepage::Windows1252;
+
+/// Decodes a null-terminated string from the codepage of the reader, or from
+/// [`FALLBACK_CODEPAGE`] if it isn't valid there, so that files which were
+/// readable before codepages were supported still are. The codepage is
+/// returned in the second case, to write the string back with the same bytes.
+fn decode_string(
+    reader: &BufferReader,
+    bytes: &[u8],
+    offset: usize,
+    expected: &'static str,
+) -> crate::ParseResult<(String, Option<Codepage>)> {
+    if let Ok(s) = reader.decode(bytes, offset, expected) {
+        return Ok((s, None));
+    }
+
+    let s = reader.decode_with(bytes, offset, expected, FALLBACK_CODEPAGE)?;
+    Ok((s, Some(FALLBACK_CODEPAGE)))
 }
 
-impl<const N: usize> FixedCString<N> {
-    fn new(bytes: &[u8; N]) -> crate::Result<Self> {
-        Ok(Self {
-            inner: CStr::from_bytes_until_nul(bytes)?.to_owned(),
-        })
-    }
+/// Null-terminated string with a fixed length.
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+pub struct FixedCString<const N: usize> {
+    inner: String,
+    /// Bytes after the null terminator. Original files often have leftovers of
+    /// longer strings there, they are written back as long as the string keeps
+    /// its length.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    trailing: Vec<u8>,
+    /// Set when the string isn't valid in the codepage of the file, so that it
+    /// is written back with the same bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    codepage: Option<Codepage>,
 }
 
 impl<const N: usize> Binary for FixedCString<N> {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
-        Self::new(&reader.read_bytes(N)?.try_into()?)
+        let offset = reader.position();
+        let bytes = reader.read_bytes(N)?.to_vec();
+
+        let Some(length) = bytes.iter().position(|&byte| byte == 0) else {
+            return Err(ParseError::new(
+                ParseErrorKind::InvalidString("missing null terminator".to_owned()),
+                offset,
+                "FixedCString",
+            ));
+        };
+
+        let (inner, codepage) = decode_string(reader, &bytes[..length], offset, "FixedCString")?;
+        let mut trailing = bytes[length + 1..].to_vec();
+
+        if trailing.iter().all(|&byte| byte == 0) {
+            trailing.clear();
+        }
+
+        Ok(Self {
+            inner,
+            trailing,
+            codepage,
+        })
     }
 
     fn to_bytes(&self, writer: &mut BufferWriter) {
-        let bytes = self.inner.as_bytes_with_nul();
-        writer.write_bytes(bytes);
-        writer.pad(N - bytes.len());
+        let codepage = self.codepage.unwrap_or(writer.codepage());
+        let mut bytes = writer.encode(&self.inner, codepage);
+
+        // One byte is reserved for the null terminator.
+        if bytes.len() >= N || bytes.contains(&0) {
+            writer.fail(format!(
+                "\"{}\" must be shorter than {N} bytes and not contain null characters",
+                self.inner
+            ));
+            bytes.truncate(N - 1);
+        }
+
+        bytes.push(0);
+        writer.write_bytes(&bytes);
+
+        if !self.trailing.is_empty() && bytes.len() + self.trailing.len() == N {
+            writer.write_bytes(&self.trailing);
+        } else {
+            writer.pad(N - bytes.len());
+        }
     }
 }
 
 impl<const N: usize> Inspector for FixedCString<N> {
     fn show(&mut self, ui: &mut egui::Ui) {
-        ui.label(self.inner.to_str().unwrap());
+        let mut s = self.inner.clone();
+
+        // One byte is reserved for the null terminator. Windows codepages use
+        // one byte per character; in UTF-8 saving fails if the string is too
+        // long.
+        if ui
+            .add(egui::TextEdit::singleline(&mut s).char_limit(N - 1))
+            .changed()
+            && !s.contains('\0')
+        {
+            self.inner = s;
+            self.trailing.clear();
+        }
     }
 }
 
 impl<T: Binary + Default + serde::Serialize> Binary for Vec<T> {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -268,7 +607,7 @@ impl<T: Binary + Default + serde::Serialize> Binary for Vec<T> {
         let mut array = Vec::with_capacity(len);
 
         for _ in 0..len {
-            array.push(T::from_bytes(reader)?);
+            array.push(T::from_bytes(reader).index(array.len())?);
         }
 
         Ok(array)
@@ -284,20 +623,27 @@ impl<T: Binary + Default + serde::Serialize> Binary for Vec<T> {
 }
 
 impl<T: Binary + serde::Serialize + Default, const N: usize> Binary for FixedArray<T, N> {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
         let mut elements = Vec::with_capacity(N);
 
         for _ in 0..N {
-            elements.push(T::from_bytes(reader)?);
+            elements.push(T::from_bytes(reader).index(elements.len())?);
         }
 
         Ok(Self { elements })
     }
 
     fn to_bytes(&self, writer: &mut BufferWriter) {
+        if self.elements.len() != N {
+            writer.fail(format!(
+                "Fixed array must have {N} elements, not {}",
+                self.elements.len()
+            ));
+        }
+
         for element in &self.elements {
             element.to_bytes(writer);
         }
@@ -335,7 +681,7 @@ impl<T: Binary + Default, const N: usize> Inspector for FixedArray<T, N> {
 }
 
 impl Binary for u32 {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -348,7 +694,7 @@ impl Binary for u32 {
 }
 
 impl Binary for String {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -361,7 +707,7 @@ impl Binary for String {
 }
 
 impl Binary for f32 {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -374,7 +720,7 @@ impl Binary for f32 {
 }
 
 impl Binary for u8 {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -387,7 +733,7 @@ impl Binary for u8 {
 }
 
 impl Binary for i16 {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -400,7 +746,7 @@ impl Binary for i16 {
 }
 
 impl Binary for u16 {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -415,39 +761,62 @@ impl Binary for u16 {
 /// Null-terminated string with a length.
 #[derive(Default, serde::Serialize, serde::Deserialize)]
 pub struct CStringWithLength {
-    inner: CString,
+    inner: String,
+    /// Set when the string isn't valid in the codepage of the file, so that it
+    /// is written back with the same bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    codepage: Option<Codepage>,
+}
+
+impl CStringWithLength {
+    /// Size in bytes when written with `codepage`, including the null terminator.
+    pub fn encoded_len(&self, codepage: Codepage) -> usize {
+        self.codepage
+            .unwrap_or(codepage)
+            .encode_lossy(&self.inner)
+            .len()
+            + 1
+    }
 }
 
 impl Binary for CStringWithLength {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
+        let offset = reader.position();
         let length = u32::from_bytes(reader)? as usize;
         let bytes = reader.read_bytes(length)?.to_vec();
 
-        Ok(Self {
-            inner: CString::from_vec_with_nul(bytes)?,
-        })
+        let Some((0, bytes)) = bytes.split_last() else {
+            return Err(ParseError::new(
+                ParseErrorKind::InvalidString("missing null terminator".to_owned()),
+                offset,
+                "CStringWithLength",
+            ));
+        };
+
+        let (inner, codepage) = decode_string(reader, bytes, offset, "CStringWithLength")?;
+        Ok(Self { inner, codepage })
     }
 
     fn to_bytes(&self, writer: &mut BufferWriter) {
-        let bytes = self.inner.as_bytes_with_nul();
+        let codepage = self.codepage.unwrap_or(writer.codepage());
+        let mut bytes = writer.encode(&self.inner, codepage);
+        bytes.push(0);
         writer.write_u32(bytes.len() as u32);
-        writer.write_bytes(bytes);
+        writer.write_bytes(&bytes);
     }
 }
 
 impl Inspector for CStringWithLength {
     fn show(&mut self, ui: &mut egui::Ui) {
-        let mut s = self.inner.to_string_lossy().to_string();
-        ui.text_edit_multiline(&mut s);
-        self.inner = CString::new(s.into_bytes()).unwrap();
+        ui.text_edit_multiline(&mut self.inner);
     }
 }
 
 impl Binary for bool {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -460,7 +829,7 @@ impl Binary for bool {
 }
 
 impl Binary for i32 {
-    fn from_bytes(reader: &mut BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -478,12 +847,14 @@ mod tests {
 
     use crate::{
         buffer::{BufferReader, BufferWriter},
+        codepage::Codepage,
         types::{
-            Binary, eggs::Eggs, info::Info, magic::Magic, music::Music,
-            osiris_objects::OsirisObjects, persist::Persist, props::Props, quest_log::QuestLog,
-            quickinfo::QuickInfo, reverbs::Reverbs, shroud::Shroud, sound::SoundConfig,
-            status_plate::StatusPlate, telpstates::TelpStates, text::Text, usernotes::Notes,
-            world::World,
+            Binary, CStringWithLength, FixedCString, Format, FormatType, eggs::Eggs, font::Font,
+            info::Info, magic::Magic, music::Music, objects_000::Objects000,
+            osiris_names::OsirisNames, osiris_objects::OsirisObjects, persist::Persist,
+            props::Props, quest_log::QuestLog, quickinfo::QuickInfo, reverbs::Reverbs,
+            shroud::Shroud, sound::SoundConfig, status_plate::StatusPlate, telpstates::TelpStates,
+            text::Text, usernotes::Notes, world::World,
         },
     };
 
@@ -492,9 +863,6 @@ mod tests {
     ///
     /// Assumes that the game directory is located at "tmp/divine_diviniy/"
     /// relative to the project root directory (can be a symlink).
-    ///
-    /// Note: Objects000 and OsirisNames are not tested because original files contain
-    /// garbage in fixed strings (after the null terminator) that gets cleaned after saving.
     #[test]
     fn load_save() {
         let path = PathBuf::from_iter([env!("CARGO_MANIFEST_DIR"), "tmp", "divine_divinity"]);
@@ -508,7 +876,7 @@ mod tests {
             let mut writer = BufferWriter::new();
             binary.to_bytes(&mut writer);
 
-            let output_file = writer.finish();
+            let output_file = writer.finish().expect("must be able to write binary file");
 
             if input_file != output_file {
                 panic!(
@@ -539,6 +907,8 @@ mod tests {
         test!(QuickInfo, "main/startup/quickinfo.000");
         test!(QuestLog, "main/startup/quest_log.000");
         test!(OsirisObjects, "main/startup/static/osiobjects.000");
+        test!(OsirisNames, "main/startup/static/osinames.000");
+        test!(Objects000, "main/startup/objects.000");
         test!(Magic, "dat/magic.cmp");
         test!(Notes, "dat/usernotes.bin");
         test!(Props, "dat/props.000");
@@ -549,7 +919,131 @@ mod tests {
         test!(Text, "localizations/english/text.cmp");
         test!(StatusPlate, "dat/statuspl.cmp");
         test!(Eggs, "global/eggs.000");
-        // TODO: implement
-        // test!(Font, "fonts/dialog_white.fnt");
+        test!(Font, "fonts/dialog_white.fnt");
+    }
+
+    #[test]
+    fn detect() {
+        let mut quest_log = b"ML3ID\0".to_vec();
+        quest_log.extend([0; 34]);
+        assert_eq!(
+            FormatType::detect(Path::new("renamed.bin"), &quest_log),
+            [FormatType::QuestLog]
+        );
+
+        let shroud = vec![0; 525826];
+        assert_eq!(
+            FormatType::detect(Path::new("backup"), &shroud),
+            [FormatType::Shroud]
+        );
+
+        // One spell record matches the magic file layout, but the file name wins.
+        let mut magic = 1u32.to_le_bytes().to_vec();
+        magic.extend([0; 28]);
+        assert_eq!(
+            FormatType::detect(Path::new("magic.cmp"), &magic)[0],
+            FormatType::Magic
+        );
+        assert_eq!(
+            FormatType::detect(Path::new("info.000"), &magic),
+            [FormatType::Info, FormatType::Magic, FormatType::SoundConfig]
+        );
+
+        assert!(FormatType::detect(Path::new("unknown"), &[1, 2, 3]).is_empty());
+
+        // Only the start of a file is needed.
+        let start = &quest_log[..FormatType::SNIFF_LEN];
+        assert!(Format::is_supported(
+            Path::new("renamed.bin"),
+            start,
+            quest_log.len()
+        ));
+        assert!(Format::is_supported(Path::new("music.dat"), &[], 0));
+        assert!(!Format::is_supported(Path::new("unknown"), start, 7));
+    }
+
+    #[test]
+    fn derive_attributes() {
+        #[derive(Default, serde::Serialize, crate::editor::Inspector, super::Binary)]
+        struct Test {
+            #[binary(pad = 2)]
+            a: u8,
+            #[binary(len = u8)]
+            b: Vec<u16>,
+            #[binary(count = 2)]
+            c: Vec<u8>,
+        }
+
+        let bytes = [1, 0, 0, 2, 3, 0, 4, 0, 5, 6];
+        let test = Test::from_bytes(&mut BufferReader::new(&bytes)).unwrap();
+        assert_eq!(
+            (test.a, &test.b[..], &test.c[..]),
+            (1, &[3, 4][..], &[5, 6][..])
+        );
+
+        let mut writer = BufferWriter::new();
+        test.to_bytes(&mut writer);
+        assert_eq!(writer.finish().unwrap(), bytes);
+
+        // Lengths that don't fit the file must not be written silently.
+        let mut test = test;
+        test.c.push(7);
+        let mut writer = BufferWriter::new();
+        test.to_bytes(&mut writer);
+        let error = writer.finish().unwrap_err();
+        assert_eq!(error.to_string(), "c must have 2 elements, not 3");
+
+        test.c.pop();
+        test.b = vec![0; 256];
+        let mut writer = BufferWriter::new();
+        test.to_bytes(&mut writer);
+        assert!(writer.finish().is_err());
+    }
+
+    #[test]
+    fn fixed_string_trailing_bytes() {
+        let bytes = *b"abc\0xyz\0";
+        let string = FixedCString::<8>::from_bytes(&mut BufferReader::new(&bytes)).unwrap();
+
+        let mut writer = BufferWriter::new();
+        string.to_bytes(&mut writer);
+        assert_eq!(writer.finish().unwrap(), bytes);
+
+        // Every character takes one byte in Windows codepages.
+        let bytes = *b"\xcf\xf0\xe8\xe2\xe5\xf2\0\0";
+        let mut reader = BufferReader::with_codepage(&bytes, Codepage::Windows1251);
+        let mut string = FixedCString::<8>::from_bytes(&mut reader).unwrap();
+        assert_eq!(string.inner, "Привет");
+
+        let mut writer = BufferWriter::with_codepage(Codepage::Windows1251);
+        string.to_bytes(&mut writer);
+        assert_eq!(writer.finish().unwrap(), bytes);
+
+        // Too long in UTF-8.
+        let mut writer = BufferWriter::new();
+        string.to_bytes(&mut writer);
+        assert!(writer.finish().is_err());
+
+        string.inner = "Привет, мир".to_owned();
+        let mut writer = BufferWriter::with_codepage(Codepage::Windows1251);
+        string.to_bytes(&mut writer);
+        assert!(writer.finish().is_err());
+    }
+
+    #[test]
+    fn string_codepages() {
+        // Not valid UTF-8, read with the fallback codepage.
+        let bytes = [3, 0, 0, 0, b'a', 0xe9, 0];
+        let string = CStringWithLength::from_bytes(&mut BufferReader::new(&bytes)).unwrap();
+        assert_eq!(string.inner, "aé");
+
+        let mut writer = BufferWriter::new();
+        string.to_bytes(&mut writer);
+        assert_eq!(writer.finish().unwrap(), bytes);
+
+        let mut writer = BufferWriter::with_codepage(Codepage::Windows1251);
+        writer.write_string("Привет, café");
+        let error = writer.finish().unwrap_err();
+        assert!(error.to_string().contains("windows-1251"), "{error}");
     }
 }
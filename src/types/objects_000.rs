@@ -1,16 +1,17 @@
 //! objects.000
 
 use crate::{
-    editor::{Inspector, property, struct_ui},
+    editor::Inspector,
+    error::{ParseError, ParseErrorKind, ParseResultExt},
     types::{Binary, FixedArray, FixedCString},
 };
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, Inspector)]
 pub struct Objects000 {
     objects: Vec<Object>,
 }
 
-#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[derive(Default, serde::Serialize, serde::Deserialize, Binary, Inspector)]
 struct Object {
     unknown0: FixedArray<u32, 8>,
     name: FixedCString<16>,
@@ -19,7 +20,7 @@ struct Object {
 }
 
 impl Binary for Objects000 {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
@@ -28,11 +29,22 @@ impl Binary for Objects000 {
         // Length is not encoded anywhere, but the size of each
         // object is 148 bytes, so we can use that.
         if !reader.len().is_multiple_of(148) {
-            return Err("this file does not appear to be valid".into());
+            return Err(ParseError::new(
+                ParseErrorKind::Corrupted(format!(
+                    "File length {} is not a multiple of 148",
+                    reader.len()
+                )),
+                0,
+                "Objects000",
+            ));
         }
 
         while !reader.is_empty() {
-            objects.push(Object::from_bytes(reader)?);
+            objects.push(
+                Object::from_bytes(reader)
+                    .index(objects.len())
+                    .field("objects")?,
+            );
         }
 
         Ok(Self { objects })
@@ -44,43 +56,3 @@ impl Binary for Objects000 {
         }
     }
 }
-
-impl Binary for Object {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
-    where
-        Self: Sized,
-    {
-        Ok(Self {
-            unknown0: <FixedArray<u32, 8>>::from_bytes(reader)?,
-            name: FixedCString::from_bytes(reader)?,
-            id: u32::from_bytes(reader)?,
-            unknown1: <FixedArray<u32, 24>>::from_bytes(reader)?,
-        })
-    }
-
-    fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
-        self.unknown0.to_bytes(writer);
-        self.name.to_bytes(writer);
-        self.id.to_bytes(writer);
-        self.unknown1.to_bytes(writer);
-    }
-}
-
-impl Inspector for Objects000 {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
-        struct_ui(ui, |ui| {
-            property("objects", &mut self.objects, ui);
-        });
-    }
-}
-
-impl Inspector for Object {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
-        struct_ui(ui, |ui| {
-            property("unknown0", &mut self.unknown0, ui);
-            property("name", &mut self.name, ui);
-            property("id", &mut self.id, ui);
-            property("unknown1", &mut self.unknown1, ui);
-        });
-    }
-}
@@ -2,7 +2,9 @@
 //! other files embedded inside them.
 
 use std::{
-    io::Write,
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
 };
 
@@ -10,11 +12,574 @@ use crate::buffer::{BufferReader, BufferWriter};
 
 const KEY: &[u8; 32] = b"\x0C\x40\x55\x0C\x2D\x41\x62\x2D\x03\x06\x48\x1E\x05\x48\x14\x05\x30\x32\x33\x34\x63\x63\x46\x33\x18\x09\x28\x0F\x06\x22\x39\x17";
 
-pub fn unpack(file_path: &Path, to_directory: &Path, assume_yes: bool) -> crate::Result<()> {
-    let file = std::fs::read(file_path).map_err(|e| format!("Failed to open .cmp file: {e}"))?;
+/// Entry of the table of contents at the start of a packed file.
+#[derive(serde::Serialize)]
+pub struct Entry {
+    /// Path of the embedded file, with backslashes as separators.
+    pub path: String,
+    pub offset: u32,
+    pub size: u32,
+    pub unknown: u32,
+}
+
+/// Name of the file that `unpack` writes next to the extracted files. It lists
+/// entries in their original order, with their original casing and unknown
+/// values, so that `pack` can reproduce the archive exactly.
+pub const MANIFEST_FILE_NAME: &str = "dt_manifest.json";
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ManifestEntry {
+    path: String,
+    unknown: u32,
+}
+
+/// Smallest possible size of an entry in the table of contents: path length,
+/// NUL, offset, size and unknown u32 value.
+const MIN_ENTRY_SIZE: usize = 17;
+
+/// Error produced when the table of contents of a packed file is damaged.
+#[derive(Debug)]
+pub enum ArchiveError {
+    /// The file is too short to contain a file count.
+    MissingFileCount,
+    /// The table of contents ends in the middle of an entry.
+    TruncatedTable { index: usize, file_count: u32 },
+    /// Decrypted entry path is not valid UTF-8.
+    InvalidPath {
+        index: usize,
+        error: std::string::FromUtf8Error,
+    },
+    /// Entry data is not located between the end of the table of contents and
+    /// the end of the file.
+    OutOfBounds {
+        index: usize,
+        path: String,
+        offset: u32,
+        size: u32,
+        data_start: usize,
+        data_end: usize,
+    },
+    /// Data of two entries overlaps.
+    Overlap {
+        index: usize,
+        path: String,
+        other_index: usize,
+        other_path: String,
+    },
+}
+
+impl std::fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingFileCount => write!(f, "file is too short to be a packed file"),
+            Self::TruncatedTable { index, file_count } => write!(
+                f,
+                "table of contents is truncated at entry {index} (expected {file_count} entries)"
+            ),
+            Self::InvalidPath { index, error } => {
+                write!(f, "entry {index} has an invalid path: {error}")
+            }
+            Self::OutOfBounds {
+                index,
+                path,
+                offset,
+                size,
+                data_start,
+                data_end,
+            } => write!(
+                f,
+                "entry {index} ({path}) with offset {offset} and size {size} \
+                 is outside of the data section ({data_start}..{data_end})"
+            ),
+            Self::Overlap {
+                index,
+                path,
+                other_index,
+                other_path,
+            } => write!(
+                f,
+                "entry {index} ({path}) overlaps entry {other_index} ({other_path})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+/// Entry as stored in the table of contents, before its path is validated.
+struct TableEntry {
+    path: Result<String, std::string::FromUtf8Error>,
+    offset: u32,
+    size: u32,
+    unknown: u32,
+}
+
+/// Reads the table of contents of a packed file without checking its entries.
+fn read_table(reader: &mut BufferReader) -> Result<Vec<TableEntry>, ArchiveError> {
+    let file_count = reader
+        .read_u32()
+        .map_err(|_| ArchiveError::MissingFileCount)?;
+
+    // Don't trust the file count when allocating, it may be garbage.
+    let mut entries = Vec::with_capacity((file_count as usize).min(reader.len() / MIN_ENTRY_SIZE));
+
+    for index in 0..file_count as usize {
+        let truncated = |_| ArchiveError::TruncatedTable { index, file_count };
+
+        let encrypted_file_path_length = reader.read_u32().map_err(truncated)? as usize;
+        let encrypted_file_path = reader
+            .read_bytes(encrypted_file_path_length)
+            .map_err(truncated)?;
+
+        let path = decrypt_file_path(encrypted_file_path);
+        reader.read_u8().map_err(truncated)?; // NUL
+
+        entries.push(TableEntry {
+            path,
+            offset: reader.read_u32().map_err(truncated)?,
+            size: reader.read_u32().map_err(truncated)?,
+            unknown: reader.read_u32().map_err(truncated)?,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Reads the table of contents of a packed file and makes sure that the data
+/// of every entry is inside the file, which is `archive_len` bytes long.
+fn read_entries(reader: &mut BufferReader, archive_len: usize) -> Result<Vec<Entry>, ArchiveError> {
+    let entries = read_table(reader)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            Ok(Entry {
+                path: entry
+                    .path
+                    .map_err(|error| ArchiveError::InvalidPath { index, error })?,
+                offset: entry.offset,
+                size: entry.size,
+                unknown: entry.unknown,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    check_entries(&entries, reader.position(), archive_len)?;
+
+    Ok(entries)
+}
+
+/// Makes sure that entry data is located in the data section
+/// (`data_start..data_end`) and that no two entries share the same bytes.
+fn check_entries(
+    entries: &[Entry],
+    data_start: usize,
+    data_end: usize,
+) -> Result<(), ArchiveError> {
+    for (index, entry) in entries.iter().enumerate() {
+        let start = entry.offset as usize;
+        let end = start + entry.size as usize;
+
+        if start < data_start || end > data_end {
+            return Err(ArchiveError::OutOfBounds {
+                index,
+                path: entry.path.clone(),
+                offset: entry.offset,
+                size: entry.size,
+                data_start,
+                data_end,
+            });
+        }
+    }
+
+    let mut sorted = (0..entries.len())
+        .filter(|&i| entries[i].size > 0)
+        .collect::<Vec<_>>();
+    sorted.sort_unstable_by_key(|&i| entries[i].offset);
+
+    for pair in sorted.windows(2) {
+        let (previous, next) = (&entries[pair[0]], &entries[pair[1]]);
+
+        if previous.offset as usize + previous.size as usize > next.offset as usize {
+            let (index, other_index) = (pair[0].max(pair[1]), pair[0].min(pair[1]));
+
+            return Err(ArchiveError::Overlap {
+                index,
+                path: entries[index].path.clone(),
+                other_index,
+                other_path: entries[other_index].path.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the raw bytes of the table of contents, leaving the rest of the file
+/// unread. If the file ends in the middle of the table, returns what was read,
+/// so that [`read_table`] can report where exactly it ends.
+fn read_table_bytes(file: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let mut table = Vec::new();
+
+    let mut read = |table: &mut Vec<u8>, count: u64| -> std::io::Result<Option<u32>> {
+        let start = table.len();
+
+        if file.take(count).read_to_end(table)? < count as usize {
+            return Ok(None);
+        }
+
+        Ok(Some(u32::from_le_bytes(
+            table[start..start + 4]
+                .try_into()
+                .expect("slice is 4 bytes long"),
+        )))
+    };
+
+    let Some(file_count) = read(&mut table, 4)? else {
+        return Ok(table);
+    };
+
+    for _ in 0..file_count {
+        let Some(encrypted_file_path_length) = read(&mut table, 4)? else {
+            break;
+        };
+
+        // + encrypted path itself
+        // + 1 for NUL
+        // + 4 for offset
+        // + 4 for size
+        // + 4 for unknown u32 value
+        if read(&mut table, encrypted_file_path_length as u64 + 13)?.is_none() {
+            break;
+        }
+    }
+
+    Ok(table)
+}
+
+/// Packed file opened for reading.
+///
+/// Only the table of contents is kept in memory, data of the entries is read
+/// from disk on demand.
+pub struct Archive {
+    /// `None` after [`Archive::close`].
+    file: Option<File>,
+    entries: Vec<Entry>,
+    /// Normalized entry path -> index of the entry.
+    index: HashMap<String, usize>,
+}
+
+impl Archive {
+    /// Opens a packed file and reads its table of contents.
+    pub fn open(file_path: &Path) -> crate::Result<Self> {
+        let file = File::open(file_path).map_err(|e| format!("Failed to open .cmp file: {e}"))?;
+        let archive_len = file.metadata()?.len() as usize;
+
+        let table = read_table_bytes(&mut BufReader::new(&file))?;
+        let entries = read_entries(&mut BufferReader::new(&table), archive_len)
+            .map_err(|e| format!("Failed to read {}: {e}", file_path.display()))?;
+
+        let mut index = HashMap::with_capacity(entries.len());
+
+        for (i, entry) in entries.iter().enumerate() {
+            index.entry(normalize_entry_path(&entry.path)).or_insert(i);
+        }
+
+        Ok(Self {
+            file: Some(file),
+            entries,
+            index,
+        })
+    }
+
+    /// Closes the file but keeps the table of contents, so the file can be
+    /// replaced while the entries are still listed. Reading entries fails
+    /// afterwards.
+    pub fn close(&mut self) {
+        self.file = None;
+    }
+
+    /// Entries in the order they are stored in the table of contents.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Finds an entry by its path. The lookup is case-insensitive, and either
+    /// backslashes (like the game does) or slashes can be used as separators.
+    pub fn entry(&self, path: &str) -> Option<&Entry> {
+        self.index
+            .get(&normalize_entry_path(path))
+            .map(|&i| &self.entries[i])
+    }
+
+    /// Reads the data of the file at `path`, see [`Archive::entry`].
+    pub fn read(&self, path: &str) -> crate::Result<Vec<u8>> {
+        let entry = self
+            .entry(path)
+            .ok_or_else(|| format!("File not found in the archive: {path}"))?;
+        self.read_entry(entry)
+    }
+
+    /// Reads the data of an entry of this archive.
+    pub fn read_entry(&self, entry: &Entry) -> crate::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(entry.size as usize);
+        self.copy_entry(entry, &mut data)?;
+        Ok(data)
+    }
+
+    /// Reads up to `count` bytes from the start of an entry, e.g. to detect
+    /// its format.
+    pub fn read_entry_start(&self, entry: &Entry, count: usize) -> crate::Result<Vec<u8>> {
+        let mut file = self.file.as_ref().ok_or("Archive is closed")?;
+        file.seek(SeekFrom::Start(entry.offset as u64))?;
+
+        let mut data = Vec::with_capacity(count);
+        file.take(count.min(entry.size as usize) as u64)
+            .read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Copies the data of an entry of this archive to `output` without
+    /// loading all of it into memory.
+    pub fn copy_entry(&self, entry: &Entry, output: &mut impl Write) -> crate::Result<()> {
+        let mut file = self.file.as_ref().ok_or("Archive is closed")?;
+        file.seek(SeekFrom::Start(entry.offset as u64))?;
+
+        if std::io::copy(&mut file.take(entry.size as u64), output)? != entry.size as u64 {
+            return Err(format!("Unexpected end of file while reading {}", entry.path).into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Prints the table of contents of a packed file, either as a table or as JSON.
+pub fn list(file_path: &Path, json: bool) -> crate::Result<()> {
+    let archive = Archive::open(file_path)?;
+    let entries = archive.entries();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(entries)?);
+        return Ok(());
+    }
+
+    println!("{:>10} {:>10} {:>10}  path", "offset", "size", "unknown");
+
+    for entry in entries {
+        println!(
+            "{:>10} {:>10} {:>10}  {}",
+            entry.offset, entry.size, entry.unknown, entry.path
+        );
+    }
+
+    println!("{} files", entries.len());
+
+    Ok(())
+}
+
+/// Selects which entries of a packed file get extracted.
+///
+/// An empty filter selects every entry.
+#[derive(Default)]
+pub struct Filter {
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+    paths: Vec<String>,
+}
+
+impl Filter {
+    /// Creates a filter from glob patterns and explicit entry paths. Both are
+    /// matched case-insensitively, and either slashes or backslashes can be used
+    /// as separators.
+    pub fn new(include: &[String], exclude: &[String], paths: &[String]) -> crate::Result<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| {
+                    glob::Pattern::new(&normalize_entry_path(p))
+                        .map_err(|e| format!("Invalid glob pattern \"{p}\": {e}"))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(Self {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+            paths: paths.iter().map(|p| normalize_entry_path(p)).collect(),
+        })
+    }
+
+    /// Whether the filter selects only some of the entries.
+    fn is_active(&self) -> bool {
+        !self.include.is_empty() || !self.exclude.is_empty() || !self.paths.is_empty()
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            require_literal_separator: false,
+            require_literal_leading_dot: false,
+        };
+        let path = normalize_entry_path(path);
+
+        let selected = (self.include.is_empty() && self.paths.is_empty())
+            || self.paths.contains(&path)
+            || self.include.iter().any(|p| p.matches_with(&path, options));
+
+        selected && !self.exclude.iter().any(|p| p.matches_with(&path, options))
+    }
+}
+
+/// Converts an entry path to the form used for matching: lowercase, with
+/// forward slashes as separators.
+fn normalize_entry_path(path: &str) -> String {
+    path.replace('\\', "/").to_lowercase()
+}
+
+/// Converts an entry path to a relative filesystem path.
+///
+/// Empty and `.` components are dropped. Absolute paths and paths with `..`
+/// components are rejected, since they could point outside of the directory
+/// the archive is extracted to.
+fn entry_file_path(path: &str) -> crate::Result<PathBuf> {
+    let error = || format!("Entry path points outside of the output directory: {path}");
+
+    if path.starts_with(['\\', '/']) {
+        return Err(error().into());
+    }
+
+    let mut file_path = PathBuf::new();
+
+    for component in path.split(['\\', '/']) {
+        match component {
+            "" | "." => {}
+            ".." => return Err(error().into()),
+            // Drive letters and alternate data streams on Windows.
+            _ if component.contains(':') => return Err(error().into()),
+            _ => file_path.push(component),
+        }
+    }
+
+    if file_path.as_os_str().is_empty() {
+        return Err(format!("Entry path is empty: \"{path}\"").into());
+    }
+
+    Ok(file_path)
+}
+
+/// Checks the table of contents of a packed file and prints every problem
+/// found. Unlike other operations, this doesn't stop at the first problem.
+pub fn verify(file_path: &Path) -> crate::Result<()> {
+    let file = File::open(file_path).map_err(|e| format!("Failed to open .cmp file: {e}"))?;
+    let file_len = file.metadata()?.len() as usize;
+    let table = read_table_bytes(&mut BufReader::new(&file))?;
+    let mut reader = BufferReader::new(&table);
+    let mut problems = Vec::new();
+
+    let entries = match read_table(&mut reader) {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("FAIL: {e}");
+            return Err("Verification failed".into());
+        }
+    };
+
+    let paths = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| match &entry.path {
+            Ok(path) => path.clone(),
+            Err(e) => {
+                problems.push(format!("entry {index} has an invalid path: {e}"));
+                String::from_utf8_lossy(e.as_bytes()).to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Data of entries is expected to follow the table of contents without gaps,
+    // in the same order as the entries.
+    let mut expected_offset = reader.position();
+    let mut seen_paths = HashMap::new();
+
+    for (index, (entry, path)) in std::iter::zip(&entries, &paths).enumerate() {
+        let start = entry.offset as usize;
+        let end = start + entry.size as usize;
+
+        if end > file_len {
+            problems.push(format!(
+                "entry {index} ({path}) ends at {end}, past the end of the file ({})",
+                file_len
+            ));
+        }
 
-    let mut reader = BufferReader::new(&file);
-    let file_count = reader.read_u32()?;
+        if start != expected_offset {
+            problems.push(format!(
+                "entry {index} ({path}) starts at {start}, expected {expected_offset}"
+            ));
+        }
+
+        expected_offset = end;
+
+        if let Err(e) = entry_file_path(path) {
+            problems.push(format!("entry {index}: {e}"));
+        }
+
+        if let Some(other_index) = seen_paths.insert(normalize_entry_path(path), index) {
+            problems.push(format!(
+                "entry {index} ({path}) has the same path as entry {other_index} ({})",
+                paths[other_index]
+            ));
+        }
+    }
+
+    if expected_offset < file_len {
+        problems.push(format!(
+            "{} unused bytes after the last entry",
+            file_len - expected_offset
+        ));
+    }
+
+    for problem in &problems {
+        println!("{problem}");
+    }
+
+    if problems.is_empty() {
+        println!("PASS: {} entries checked", entries.len());
+        Ok(())
+    } else {
+        println!(
+            "FAIL: {} problems found in {} entries",
+            problems.len(),
+            entries.len()
+        );
+        Err("Verification failed".into())
+    }
+}
+
+pub fn unpack(
+    file_path: &Path,
+    to_directory: &Path,
+    filter: &Filter,
+    assume_yes: bool,
+) -> crate::Result<()> {
+    let archive = Archive::open(file_path)?;
+
+    let entries = archive
+        .entries()
+        .iter()
+        .filter(|entry| filter.matches(&entry.path))
+        .collect::<Vec<_>>();
+
+    for path in &filter.paths {
+        let Some(entry) = archive.entry(path) else {
+            return Err(format!("File not found in the archive: {path}").into());
+        };
+
+        if !filter.matches(&entry.path) {
+            return Err(format!("File is excluded by the filter: {}", entry.path).into());
+        }
+    }
+
+    let file_count = entries.len();
 
     if !assume_yes {
         print!(
@@ -35,43 +600,64 @@ pub fn unpack(file_path: &Path, to_directory: &Path, assume_yes: bool) -> crate:
         }
     }
 
-    for _ in 0..file_count {
-        let encrypted_file_path_length = reader.read_u32()? as usize;
-        let encrypted_file_path = reader.read_bytes(encrypted_file_path_length)?;
-
-        let decrypted_file_path = decrypt_file_path(encrypted_file_path)
-            .map_err(|e| format!("Failed to decrypt file path: {e}"))?;
-        reader.skip(1); // NUL
+    // Validate every path before writing anything, so a malicious archive
+    // doesn't leave a half-extracted directory behind.
+    let file_paths = entries
+        .iter()
+        .map(|entry| entry_file_path(&entry.path))
+        .collect::<crate::Result<Vec<_>>>()?;
 
-        let file_offset = reader.read_u32()? as usize;
+    for (entry, file_path) in std::iter::zip(&entries, file_paths) {
+        let output_path = to_directory.join(&file_path);
 
-        let file_size = reader.read_u32()? as usize;
-
-        let _unknown = reader.read_u32()?;
-
-        let dir = decrypted_file_path
+        let dir = output_path
             .parent()
-            .expect("decrypted path must have a parent directory");
+            .expect("entry path must have a parent directory");
         std::fs::create_dir_all(dir)?;
 
-        println!("Unpacked {}", decrypted_file_path.display());
+        println!("Unpacked {}", file_path.display());
+
+        let mut output_file = File::create(output_path)
+            .map_err(|e| format!("Failed to write unpacked file to a file: {e}"))?;
+        archive
+            .copy_entry(entry, &mut output_file)
+            .map_err(|e| format!("Failed to write unpacked file to a file: {e}"))?;
+    }
 
-        std::fs::write(
-            to_directory.join(decrypted_file_path),
-            &file[file_offset..file_offset + file_size],
-        )
-        .map_err(|e| format!("Failed to write unpacked file to a file: {e}"))?;
+    // A manifest of only some of the entries would make `pack` lose the order
+    // and unknown values of the others.
+    if filter.is_active() {
+        println!("Not writing {MANIFEST_FILE_NAME}, because not every file was extracted");
+        return Ok(());
     }
 
+    let manifest = entries
+        .into_iter()
+        .map(|entry| ManifestEntry {
+            path: entry.path.clone(),
+            unknown: entry.unknown,
+        })
+        .collect::<Vec<_>>();
+
+    std::fs::write(
+        to_directory.join(MANIFEST_FILE_NAME),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .map_err(|e| format!("Failed to write {MANIFEST_FILE_NAME}: {e}"))?;
+
     Ok(())
 }
 
 pub fn pack(directory: &Path, output: &Path) -> crate::Result<()> {
     let base_dir = directory.to_string_lossy().to_string();
-    let mut file_paths = Vec::new();
-    let mut files = Vec::new();
+    // The output file may be inside the directory we are packing (e.g. left over
+    // from a previous run), it must not end up inside the new archive.
+    let output_path = output.canonicalize().ok();
+    let manifest_path = directory.join(MANIFEST_FILE_NAME);
+
+    // (path inside the archive, path on disk), taken out once added to the archive.
+    let mut found_files = Vec::new();
 
-    let mut current_offset = 4u32;
     let mut stack = Vec::new();
     stack.push(directory.to_path_buf());
 
@@ -89,7 +675,11 @@ pub fn pack(directory: &Path, output: &Path) -> crate::Result<()> {
         }
 
         if parent.is_file() {
-            let file = std::fs::read(&parent)?;
+            if parent == manifest_path
+                || (output_path.is_some() && parent.canonicalize().ok() == output_path)
+            {
+                continue;
+            }
 
             let converted_path = parent
                 .strip_prefix(&base_dir)?
@@ -97,55 +687,260 @@ pub fn pack(directory: &Path, output: &Path) -> crate::Result<()> {
                 .to_string()
                 .replace("/", "\\");
 
-            println!("Packing {}", converted_path);
-            let encrypted_path = encrypt_file_path(&converted_path);
+            found_files.push(Some((converted_path, parent)));
+        }
+    }
+
+    // (path inside the archive, path on disk, unknown u32 value)
+    let mut packed_files = Vec::with_capacity(found_files.len());
+
+    if manifest_path.is_file() {
+        let manifest = std::fs::read_to_string(&manifest_path)?;
+        let manifest = serde_json::from_str::<Vec<ManifestEntry>>(&manifest)
+            .map_err(|e| format!("Failed to read {MANIFEST_FILE_NAME}: {e}"))?;
 
-            // + 4 for path length
-            // + encrypted path itself
-            // + 4 for offset
-            // + 4 for size
-            // + 4 for unknown u32 value
-            current_offset += encrypted_path.len() as u32 + 16;
+        let mut found_indices = found_files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, file)| Some((normalize_entry_path(&file.as_ref()?.0), i)))
+            .collect::<HashMap<_, _>>();
 
-            files.push(file);
-            file_paths.push(encrypted_path);
+        for entry in manifest {
+            let Some(index) = found_indices.remove(&normalize_entry_path(&entry.path)) else {
+                println!(
+                    "Skipping {} (listed in {MANIFEST_FILE_NAME}, but does not exist)",
+                    entry.path
+                );
+                continue;
+            };
+
+            let (_, file_path) = found_files[index].take().expect("file is only taken once");
+            packed_files.push((entry.path, file_path, entry.unknown));
         }
     }
 
-    let mut output_file = BufferWriter::new();
-    output_file.write_u32(files.len() as u32);
+    // Files that are not in the manifest go after the ones that are.
+    packed_files.extend(
+        found_files
+            .into_iter()
+            .flatten()
+            .map(|(path, file_path)| (path, file_path, 0)),
+    );
+
+    let mut files = Vec::with_capacity(packed_files.len());
+
+    for (path, file_path, unknown) in packed_files {
+        println!("Packing {}", path);
+        let size = file_path.metadata()?.len();
+
+        files.push(PackedFile {
+            size: size
+                .try_into()
+                .map_err(|_| format!("File is too large to be packed: {path}"))?,
+            path,
+            unknown,
+            source: Source::Path(file_path),
+        });
+    }
+
+    let mut output_file = BufWriter::new(File::create(output)?);
+    write_archive(&mut output_file, &files)?;
+    output_file.flush()?;
+
+    Ok(())
+}
+
+/// Where the data of a [`PackedFile`] comes from.
+enum Source<'a> {
+    /// File on disk.
+    Path(PathBuf),
+    /// Part of an already opened file (e.g. the archive that is being
+    /// modified), starting at the given offset.
+    Range(&'a File, u64),
+    /// Data in memory.
+    Bytes(Vec<u8>),
+}
+
+/// File to be written into a packed file.
+struct PackedFile<'a> {
+    /// Path inside the archive, with backslashes as separators.
+    path: String,
+    unknown: u32,
+    size: u32,
+    source: Source<'a>,
+}
+
+/// Writes a packed file, placing the data of files right after the table of
+/// contents in the same order as the entries.
+///
+/// The table of contents is written first, then the data of every file is
+/// copied from its source one by one, so only one file is read at a time.
+fn write_archive(output: &mut impl Write, files: &[PackedFile]) -> crate::Result<()> {
+    let encrypted_paths = files
+        .iter()
+        .map(|file| encrypt_file_path(&file.path))
+        .collect::<Vec<_>>();
+
+    // + 4 for path length
+    // + encrypted path itself
+    // + 4 for offset
+    // + 4 for size
+    // + 4 for unknown u32 value
+    let table_len = 4 + encrypted_paths
+        .iter()
+        .map(|path| path.len() as u64 + 16)
+        .sum::<u64>();
+
+    let archive_len = table_len + files.iter().map(|file| file.size as u64).sum::<u64>();
+
+    if archive_len > u32::MAX as u64 {
+        return Err("Packed file would be larger than 4 GiB".into());
+    }
+
+    let mut current_offset = table_len as u32;
 
-    for (file_path, bytes) in std::iter::zip(file_paths, &files) {
-        output_file.write_u32(file_path.len() as u32 - 1);
-        output_file.write_bytes(&file_path);
-        output_file.write_u32(current_offset);
+    let mut table = BufferWriter::new();
+    table.write_u32(files.len() as u32);
 
-        let file_size = bytes.len() as u32;
-        output_file.write_u32(file_size);
+    for (file, encrypted_path) in std::iter::zip(files, &encrypted_paths) {
+        table.write_u32(encrypted_path.len() as u32 - 1);
+        table.write_bytes(encrypted_path);
+        table.write_u32(current_offset);
+        table.write_u32(file.size);
 
-        current_offset += file_size;
+        current_offset += file.size;
 
-        // Unknown.
-        output_file.write_u32(0);
+        table.write_u32(file.unknown);
     }
 
+    output.write_all(&table.finish()?)?;
+
     for file in files {
-        output_file.write_bytes(&file);
+        let copied = match &file.source {
+            Source::Path(path) => {
+                std::io::copy(&mut File::open(path)?.take(file.size as u64), output)?
+            }
+            Source::Range(archive, offset) => {
+                let mut archive = *archive;
+                archive.seek(SeekFrom::Start(*offset))?;
+                std::io::copy(&mut archive.take(file.size as u64), output)?
+            }
+            Source::Bytes(bytes) => {
+                output.write_all(bytes)?;
+                bytes.len() as u64
+            }
+        };
+
+        if copied != file.size as u64 {
+            return Err(format!("{} was modified while it was being packed", file.path).into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Operation applied to a single entry by [`modify`].
+pub enum Modification {
+    /// Adds a new entry at the end of the archive.
+    Add { data: Vec<u8> },
+    /// Replaces data of an existing entry, keeping its position, path casing
+    /// and unknown value.
+    Replace { data: Vec<u8> },
+    /// Removes an existing entry.
+    Remove,
+}
+
+/// Adds, replaces or removes a single entry of a packed file in place.
+pub fn modify(
+    archive_path: &Path,
+    entry_path: &str,
+    modification: Modification,
+) -> crate::Result<()> {
+    let archive = Archive::open(archive_path)?;
+
+    // Make sure a new path is safe to unpack later. Existing entries with bad
+    // paths (see `verify`) must stay replaceable and removable.
+    if let Modification::Add { .. } = modification {
+        entry_file_path(entry_path)?;
     }
+    let entry_path = entry_path.replace('/', "\\");
+    let normalized_path = normalize_entry_path(&entry_path);
+
+    let mut files = archive
+        .entries()
+        .iter()
+        .map(|entry| PackedFile {
+            path: entry.path.clone(),
+            unknown: entry.unknown,
+            size: entry.size,
+            source: Source::Range(
+                archive.file.as_ref().expect("archive was just opened"),
+                entry.offset as u64,
+            ),
+        })
+        .collect::<Vec<_>>();
+
+    let index = files
+        .iter()
+        .position(|file| normalize_entry_path(&file.path) == normalized_path);
+
+    let size = |data: &[u8]| {
+        u32::try_from(data.len())
+            .map_err(|_| format!("{entry_path} is too large for a packed file (4 GiB or more)"))
+    };
+
+    match (modification, index) {
+        (Modification::Add { .. }, Some(index)) => {
+            return Err(
+                format!("File already exists in the archive: {}", files[index].path).into(),
+            );
+        }
+        (Modification::Add { data }, None) => {
+            files.push(PackedFile {
+                size: size(&data)?,
+                path: entry_path,
+                unknown: 0,
+                source: Source::Bytes(data),
+            });
+        }
+        (Modification::Replace { data }, Some(index)) => {
+            files[index].size = size(&data)?;
+            files[index].source = Source::Bytes(data);
+        }
+        (Modification::Remove, Some(index)) => {
+            files.remove(index);
+        }
+        (Modification::Replace { .. } | Modification::Remove, None) => {
+            return Err(format!("File not found in the archive: {entry_path}").into());
+        }
+    }
+
+    // Write to a temporary file first, so the archive isn't left half-written
+    // if something goes wrong.
+    let temporary_path = archive_path.with_added_extension("tmp");
+    let mut output_file = BufWriter::new(File::create(&temporary_path)?);
+    write_archive(&mut output_file, &files)
+        .and_then(|_| Ok(output_file.flush()?))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&temporary_path);
+        })?;
+
+    // The archive must be closed before it can be replaced on Windows.
+    drop(files);
+    drop(archive);
+    std::fs::rename(&temporary_path, archive_path)?;
 
-    std::fs::write(output, output_file.finish())?;
     Ok(())
 }
 
-fn decrypt_file_path(encrypted_buffer: &[u8]) -> crate::Result<PathBuf> {
+fn decrypt_file_path(encrypted_buffer: &[u8]) -> Result<String, std::string::FromUtf8Error> {
     let mut decrypted_buffer = encrypted_buffer.to_vec();
 
     for (i, byte) in decrypted_buffer.iter_mut().enumerate() {
         *byte = !*byte ^ KEY[i % 32];
     }
 
-    let path = String::from_utf8(decrypted_buffer)?;
-    Ok(path.split('\\').collect())
+    String::from_utf8(decrypted_buffer)
 }
 
 fn encrypt_file_path(path: &str) -> Vec<u8> {
@@ -158,3 +953,228 @@ fn encrypt_file_path(path: &str) -> Vec<u8> {
     encrypted_buffer.push(b'\0');
     encrypted_buffer
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        buffer::{BufferReader, BufferWriter},
+        types::packed::{
+            Archive, ArchiveError, Filter, MANIFEST_FILE_NAME, Modification, encrypt_file_path,
+            entry_file_path, modify, pack, read_entries, unpack,
+        },
+    };
+
+    /// Builds a packed file with the given (path, offset, size) entries and
+    /// `data_length` bytes of data after the table of contents.
+    fn archive(entries: &[(&str, u32, u32)], data_length: usize) -> Vec<u8> {
+        let mut writer = BufferWriter::new();
+        writer.write_u32(entries.len() as u32);
+
+        for (path, offset, size) in entries {
+            let encrypted_path = encrypt_file_path(path);
+            writer.write_u32(encrypted_path.len() as u32 - 1);
+            writer.write_bytes(&encrypted_path);
+            writer.write_u32(*offset);
+            writer.write_u32(*size);
+            writer.write_u32(0);
+        }
+
+        writer.pad(data_length);
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn filter() {
+        let all = Filter::new(&[], &[], &[]).unwrap();
+        assert!(all.matches("a.txt"));
+        assert!(all.matches("Sub\\B.dat"));
+
+        let filter = Filter::new(&["sub/*".into()], &["*.WAV".into()], &["A.TXT".into()]).unwrap();
+        assert!(filter.matches("a.txt"));
+        assert!(filter.matches("Sub\\B.dat"));
+        assert!(!filter.matches("Sub\\C.wav"));
+        assert!(!filter.matches("b.txt"));
+    }
+
+    #[test]
+    fn entry_paths() {
+        assert_eq!(
+            entry_file_path("Sub\\.\\B.dat").unwrap(),
+            PathBuf::from_iter(["Sub", "B.dat"])
+        );
+        assert!(entry_file_path("..\\a.txt").is_err());
+        assert!(entry_file_path("Sub\\..\\..\\a.txt").is_err());
+        assert!(entry_file_path("\\Windows\\a.txt").is_err());
+        assert!(entry_file_path("/etc/passwd").is_err());
+        assert!(entry_file_path("C:\\a.txt").is_err());
+        assert!(entry_file_path("").is_err());
+    }
+
+    #[test]
+    fn entry_bounds() {
+        // Table of contents of two entries with single-character paths is 4 + 2 * 18 bytes long.
+        let valid = archive(&[("a", 40, 2), ("b", 42, 3)], 5);
+        assert!(read_entries(&mut BufferReader::new(&valid), valid.len()).is_ok());
+
+        let truncated = archive(&[("a", 40, 2), ("b", 42, 3)], 4);
+        assert!(matches!(
+            read_entries(&mut BufferReader::new(&truncated), truncated.len()),
+            Err(ArchiveError::OutOfBounds { index: 1, .. })
+        ));
+
+        let inside_table = archive(&[("a", 20, 2), ("b", 42, 3)], 5);
+        assert!(matches!(
+            read_entries(&mut BufferReader::new(&inside_table), inside_table.len()),
+            Err(ArchiveError::OutOfBounds { index: 0, .. })
+        ));
+
+        let overlapping = archive(&[("a", 40, 3), ("b", 42, 3)], 5);
+        assert!(matches!(
+            read_entries(&mut BufferReader::new(&overlapping), overlapping.len()),
+            Err(ArchiveError::Overlap {
+                index: 1,
+                other_index: 0,
+                ..
+            })
+        ));
+
+        assert!(matches!(
+            read_entries(&mut BufferReader::new(&valid[..30]), 30),
+            Err(ArchiveError::TruncatedTable { index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn archive_lookup() {
+        // Table of contents is 51 bytes long.
+        let mut bytes = archive(&[("Dir\\File.TXT", 51, 2), ("b", 53, 3)], 0);
+        bytes.extend_from_slice(b"abcde");
+
+        let path =
+            std::env::temp_dir().join(format!("dt_archive_lookup_{}.cmp", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let archive = Archive::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        let archive = archive.unwrap();
+
+        assert_eq!(archive.entries().len(), 2);
+        assert_eq!(archive.read("dir/file.txt").unwrap(), b"ab");
+        assert_eq!(archive.read("DIR\\FILE.TXT").unwrap(), b"ab");
+        assert_eq!(archive.read("B").unwrap(), b"cde");
+        assert!(archive.entry("c").is_none());
+    }
+
+    #[test]
+    fn entry_without_file_name() {
+        // Table of contents is 25 bytes long.
+        let mut bytes = archive(&[("dir\\", 25, 1)], 0);
+        bytes.push(0);
+
+        let path = std::env::temp_dir().join(format!("dt_no_name_{}.cmp", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let archive = Archive::open(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let error = crate::types::Format::from_archive(&archive.unwrap(), "dir\\")
+            .err()
+            .expect("entry without a file name must fail to load");
+        assert!(error.to_string().starts_with("Not a file name"), "{error}");
+    }
+
+    #[test]
+    fn modify_entries() {
+        // Table of contents is 80 bytes long.
+        let mut bytes = archive(
+            &[
+                ("a.txt", 80, 2),
+                ("Sub\\b.txt", 82, 3),
+                ("..\\evil.txt", 85, 1),
+            ],
+            0,
+        );
+        bytes.extend_from_slice(b"abcde!");
+
+        let path = std::env::temp_dir().join(format!("dt_modify_{}.cmp", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+
+        let result = (|| -> crate::Result<Vec<(String, Vec<u8>)>> {
+            let add = |data: &[u8]| Modification::Add {
+                data: data.to_vec(),
+            };
+
+            modify(&path, "new/c.txt", add(b"xyz"))?;
+            modify(
+                &path,
+                "sub/B.TXT",
+                Modification::Replace {
+                    data: b"1234".to_vec(),
+                },
+            )?;
+            modify(&path, "a.txt", Modification::Remove)?;
+            // Entries with unsafe paths can be removed, but not added.
+            modify(&path, "..\\evil.txt", Modification::Remove)?;
+
+            assert!(modify(&path, "a.txt", Modification::Remove).is_err());
+            assert!(modify(&path, "NEW\\C.TXT", add(b"")).is_err());
+            assert!(modify(&path, "../evil.txt", add(b"")).is_err());
+
+            let archive = Archive::open(&path)?;
+            archive
+                .entries()
+                .iter()
+                .map(|entry| Ok((entry.path.clone(), archive.read_entry(entry)?)))
+                .collect()
+        })();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            result.unwrap(),
+            [
+                ("Sub\\b.txt".to_owned(), b"1234".to_vec()),
+                ("new\\c.txt".to_owned(), b"xyz".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unpack_pack_round_trip() {
+        // Table of contents is 51 bytes long.
+        let mut bytes = archive(&[("Dir\\File.TXT", 51, 2), ("b", 53, 3)], 0);
+        bytes.extend_from_slice(b"abcde");
+
+        let directory = std::env::temp_dir().join(format!("dt_unpack_pack_{}", std::process::id()));
+        let unpacked = directory.join("unpacked");
+        let input = directory.join("input.cmp");
+        let output = directory.join("output.cmp");
+
+        let result = (|| -> crate::Result<Vec<u8>> {
+            std::fs::create_dir_all(&unpacked)?;
+            std::fs::write(&input, &bytes)?;
+
+            unpack(&input, &unpacked, &Filter::new(&[], &[], &[])?, true)?;
+
+            // Extracting only some files must not replace the full manifest.
+            let manifest = std::fs::read(unpacked.join(MANIFEST_FILE_NAME))?;
+            unpack(
+                &input,
+                &unpacked,
+                &Filter::new(&[], &[], &["b".into()])?,
+                true,
+            )?;
+            assert_eq!(std::fs::read(unpacked.join(MANIFEST_FILE_NAME))?, manifest);
+
+            // Explicit paths that are excluded aren't reported as missing.
+            let filter = Filter::new(&[], &["B".into()], &["b".into()])?;
+            let error = unpack(&input, &unpacked, &filter, true).unwrap_err();
+            assert!(error.to_string().contains("excluded"), "{error}");
+
+            pack(&unpacked, &output)?;
+            Ok(std::fs::read(&output)?)
+        })();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(result.unwrap(), bytes);
+    }
+}
@@ -1,8 +1,18 @@
 //! quickinfo.000
 
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+};
+
+use eframe::egui;
+
 use crate::{
+    codepage::Codepage,
     editor::{Inspector, property, property_read_only, struct_ui},
-    types::{Binary, FixedCString},
+    error::{ParseError, ParseErrorKind, ParseResultExt},
+    image::RgbImage,
+    types::{Binary, FixedCString, read_file, write_file},
 };
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -14,27 +24,202 @@ pub struct QuickInfo {
     thumbnail_height: u32,
     game_version: FixedCString<64>,
     save_version: FixedCString<64>,
-    // Either RGB555 or RGB565, not sure.
+    /// See [`PixelFormat`].
     thumbnail_image_data: Vec<u16>,
 }
 
+/// Format of the thumbnail pixels. It's not known which one the game uses,
+/// so it can be chosen when viewing or converting the thumbnail.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Hash, Inspector)]
+pub enum PixelFormat {
+    #[default]
+    #[inspector(label = "RGB565")]
+    Rgb565,
+    #[inspector(label = "RGB555")]
+    Rgb555,
+}
+
+impl PixelFormat {
+    pub fn decode(self, pixel: u16) -> [u8; 3] {
+        // Repeating the high bits fills the whole 0-255 range.
+        let expand5 = |c: u16| ((c << 3) | (c >> 2)) as u8;
+        let expand6 = |c: u16| ((c << 2) | (c >> 4)) as u8;
+
+        match self {
+            PixelFormat::Rgb565 => [
+                expand5((pixel >> 11) & 0x1f),
+                expand6((pixel >> 5) & 0x3f),
+                expand5(pixel & 0x1f),
+            ],
+            PixelFormat::Rgb555 => [
+                expand5((pixel >> 10) & 0x1f),
+                expand5((pixel >> 5) & 0x1f),
+                expand5(pixel & 0x1f),
+            ],
+        }
+    }
+
+    pub fn encode(self, [r, g, b]: [u8; 3]) -> u16 {
+        let (r, g, b) = (r as u16, g as u16, b as u16);
+
+        match self {
+            PixelFormat::Rgb565 => ((r >> 3) << 11) | ((g >> 2) << 5) | (b >> 3),
+            PixelFormat::Rgb555 => ((r >> 3) << 10) | ((g >> 3) << 5) | (b >> 3),
+        }
+    }
+}
+
+impl std::fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PixelFormat::Rgb565 => f.write_str("rgb565"),
+            PixelFormat::Rgb555 => f.write_str("rgb555"),
+        }
+    }
+}
+
+impl std::str::FromStr for PixelFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [PixelFormat::Rgb565, PixelFormat::Rgb555]
+            .into_iter()
+            .find(|format| format.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown pixel format: {s}"))
+    }
+}
+
+impl QuickInfo {
+    pub fn thumbnail(&self, format: PixelFormat) -> RgbImage {
+        RgbImage {
+            width: self.thumbnail_width as usize,
+            height: self.thumbnail_height as usize,
+            pixels: self
+                .thumbnail_image_data
+                .iter()
+                .map(|&pixel| format.decode(pixel))
+                .collect(),
+        }
+    }
+
+    /// The image is resized to the size of the current thumbnail, which the
+    /// game expects.
+    pub fn set_thumbnail(&mut self, image: &RgbImage, format: PixelFormat) {
+        let image = image.resize(
+            self.thumbnail_width as usize,
+            self.thumbnail_height as usize,
+        );
+
+        self.thumbnail_image_data = image
+            .pixels
+            .iter()
+            .map(|&pixel| format.encode(pixel))
+            .collect();
+    }
+
+    fn show_thumbnail(&self, ui: &mut egui::Ui) {
+        let format_id = egui::Id::new("thumbnail_pixel_format");
+        let mut format: PixelFormat = ui.data(|d| d.get_temp(format_id)).unwrap_or_default();
+        format.show(ui);
+        ui.data_mut(|d| d.insert_temp(format_id, format));
+
+        let (width, height) = (
+            self.thumbnail_width as usize,
+            self.thumbnail_height as usize,
+        );
+        if self.thumbnail_image_data.len() != width * height {
+            ui.label("The size of the image data doesn't match the thumbnail size");
+            return;
+        }
+
+        // The texture is only uploaded again when the image or the format change.
+        let mut hasher = DefaultHasher::new();
+        (format, &self.thumbnail_image_data).hash(&mut hasher);
+        let key = hasher.finish();
+
+        let texture_id = ui.id().with("thumbnail_texture");
+        let texture = match ui.data(|d| d.get_temp::<(u64, egui::TextureHandle)>(texture_id)) {
+            Some((cached_key, texture)) if cached_key == key => texture,
+            _ => {
+                let pixels = self.thumbnail(format).pixels;
+                let image = egui::ColorImage::from_rgb([width, height], pixels.as_flattened());
+                let texture =
+                    ui.ctx()
+                        .load_texture("thumbnail", image, egui::TextureOptions::NEAREST);
+                ui.data_mut(|d| d.insert_temp(texture_id, (key, texture.clone())));
+                texture
+            }
+        };
+
+        ui.image((texture.id(), texture.size_vec2() * 2.0));
+    }
+}
+
+/// Writes the thumbnail of a quickinfo file to a PNG image.
+pub fn export_thumbnail(
+    path: &Path,
+    output: &Path,
+    format: PixelFormat,
+    codepage: Codepage,
+) -> crate::Result<()> {
+    let quick_info: QuickInfo = read_file(path, codepage)?;
+    quick_info.thumbnail(format).save(output)
+}
+
+/// Replaces the thumbnail of a quickinfo file with a PNG image.
+pub fn import_thumbnail(
+    path: &Path,
+    image: &Path,
+    output: &Path,
+    format: PixelFormat,
+    codepage: Codepage,
+) -> crate::Result<()> {
+    let mut quick_info: QuickInfo = read_file(path, codepage)?;
+    quick_info.set_thumbnail(&RgbImage::load(image)?, format);
+    write_file(output, &quick_info, codepage)
+}
+
 impl Binary for QuickInfo {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
-        let unknown0 = u32::from_bytes(reader)?;
-        let player_name = FixedCString::from_bytes(reader)?;
-        let thumbnail_width = u32::from_bytes(reader)?;
-        let thumbnail_height = u32::from_bytes(reader)?;
-        let game_version = FixedCString::from_bytes(reader)?;
-        let save_version = FixedCString::from_bytes(reader)?;
-
-        let mut thumbnail_image_data =
-            Vec::with_capacity((thumbnail_width * thumbnail_height) as usize);
-
-        for _ in 0..thumbnail_width * thumbnail_height {
-            thumbnail_image_data.push(reader.read_u16()?);
+        let unknown0 = u32::from_bytes(reader).field("unknown0")?;
+        let player_name = FixedCString::from_bytes(reader).field("player_name")?;
+        let thumbnail_width = u32::from_bytes(reader).field("thumbnail_width")?;
+        let thumbnail_height = u32::from_bytes(reader).field("thumbnail_height")?;
+        let game_version = FixedCString::from_bytes(reader).field("game_version")?;
+        let save_version = FixedCString::from_bytes(reader).field("save_version")?;
+
+        // The size comes from the file, so check it before reserving memory.
+        let offset = reader.position();
+        let remaining = reader.len().saturating_sub(offset);
+        let pixel_count = (thumbnail_width as usize)
+            .checked_mul(thumbnail_height as usize)
+            .filter(|&count| count <= remaining / 2)
+            .ok_or_else(|| {
+                ParseError::new(
+                    ParseErrorKind::UnexpectedEof {
+                        needed: (thumbnail_width as usize)
+                            .saturating_mul(thumbnail_height as usize)
+                            .saturating_mul(2),
+                        remaining,
+                    },
+                    offset,
+                    "u16",
+                )
+            })
+            .field("thumbnail_image_data")?;
+
+        let mut thumbnail_image_data = Vec::with_capacity(pixel_count);
+
+        for _ in 0..pixel_count {
+            thumbnail_image_data.push(
+                reader
+                    .read_u16()
+                    .index(thumbnail_image_data.len())
+                    .field("thumbnail_image_data")?,
+            );
         }
 
         Ok(Self {
@@ -56,6 +241,16 @@ impl Binary for QuickInfo {
         self.game_version.to_bytes(writer);
         self.save_version.to_bytes(writer);
 
+        let pixel_count = self.thumbnail_width as usize * self.thumbnail_height as usize;
+        if self.thumbnail_image_data.len() != pixel_count {
+            writer.fail(format!(
+                "thumbnail_image_data must have {pixel_count} elements for a {}x{} thumbnail, not {}",
+                self.thumbnail_width,
+                self.thumbnail_height,
+                self.thumbnail_image_data.len()
+            ));
+        }
+
         for color in &self.thumbnail_image_data {
             color.to_bytes(writer);
         }
@@ -63,7 +258,7 @@ impl Binary for QuickInfo {
 }
 
 impl Inspector for QuickInfo {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
+    fn show(&mut self, ui: &mut egui::Ui) {
         struct_ui(ui, |ui| {
             property("unknown0", &mut self.unknown0, ui);
             property("player_name", &mut self.player_name, ui);
@@ -71,7 +266,71 @@ impl Inspector for QuickInfo {
             property_read_only("thumbnail_height", &mut self.thumbnail_height, ui);
             property("game_version", &mut self.game_version, ui);
             property("save_version", &mut self.save_version, ui);
-            property_read_only("thumbnail_image_data", &mut self.thumbnail_image_data, ui);
+
+            ui.label("thumbnail");
+            ui.vertical(|ui| self.show_thumbnail(ui));
+            ui.end_row();
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PixelFormat, QuickInfo};
+    use crate::{
+        buffer::{BufferReader, BufferWriter},
+        types::Binary,
+    };
+
+    fn file_bytes(width: u32, height: u32, pixels: &[u16]) -> Vec<u8> {
+        let mut writer = BufferWriter::new();
+        writer
+            .write_u32(0)
+            .pad(64)
+            .write_u32(width)
+            .write_u32(height);
+        writer.pad(128);
+        for &pixel in pixels {
+            writer.write_u16(pixel);
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn thumbnail_size() {
+        let bytes = file_bytes(2, 1, &[1, 2]);
+        let mut quick_info = QuickInfo::from_bytes(&mut BufferReader::new(&bytes)).unwrap();
+
+        let mut writer = BufferWriter::new();
+        quick_info.to_bytes(&mut writer);
+        assert_eq!(writer.finish().unwrap(), bytes);
+
+        quick_info.thumbnail_image_data.pop();
+        let mut writer = BufferWriter::new();
+        quick_info.to_bytes(&mut writer);
+        assert!(writer.finish().is_err());
+
+        // Sizes that don't fit the file must fail before reserving memory.
+        for (width, height) in [(u32::MAX, u32::MAX), (3, 1)] {
+            let bytes = file_bytes(width, height, &[1, 2]);
+            let error = match QuickInfo::from_bytes(&mut BufferReader::new(&bytes)) {
+                Ok(_) => panic!("{width}x{height} thumbnail must fail to load"),
+                Err(error) => error,
+            };
+            assert_eq!(error.path(), "thumbnail_image_data");
+        }
+    }
+
+    #[test]
+    fn thumbnail_pixel_formats() {
+        for format in [PixelFormat::Rgb565, PixelFormat::Rgb555] {
+            for pixel in [0x0000, 0x7fff, 0x1234, 0x4321] {
+                assert_eq!(format.encode(format.decode(pixel)), pixel, "{format}");
+            }
+        }
+
+        assert_eq!(PixelFormat::Rgb565.decode(0xffff), [255, 255, 255]);
+        assert_eq!(PixelFormat::Rgb565.decode(0xf800), [255, 0, 0]);
+        assert_eq!(PixelFormat::Rgb555.decode(0x7c00), [255, 0, 0]);
+    }
+}
@@ -1,6 +1,19 @@
 //! shroud.xN
 
-use crate::{editor::Inspector, types::Binary};
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+};
+
+use eframe::egui::{self, DragValue, Sense, Slider};
+
+use crate::{
+    codepage::Codepage,
+    editor::Inspector,
+    error::{ParseError, ParseErrorKind, ParseResultExt},
+    image::GrayImage,
+    types::{Binary, read_file, write_file},
+};
 
 const WIDTH: usize = 513;
 const HEIGHT: usize = 1025;
@@ -13,21 +26,27 @@ pub struct Shroud {
 }
 
 impl Binary for Shroud {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
         // Every shroud file seems to be 525826 bytes long.
         if reader.len() != 525826 {
-            return Err("Corrupted shroud file (or not a shroud file at all)".into());
+            return Err(ParseError::new(
+                ParseErrorKind::Corrupted(
+                    "Corrupted shroud file (or not a shroud file at all)".to_owned(),
+                ),
+                0,
+                "Shroud",
+            ));
         }
 
-        let unknown0 = u8::from_bytes(reader)?;
+        let unknown0 = u8::from_bytes(reader).field("unknown0")?;
 
         let mut cells = Vec::with_capacity(WIDTH * HEIGHT);
 
         for _ in 0..WIDTH * HEIGHT {
-            cells.push(reader.read_u8()?);
+            cells.push(reader.read_u8().index(cells.len()).field("cells")?);
         }
 
         Ok(Self { unknown0, cells })
@@ -42,10 +61,169 @@ impl Binary for Shroud {
     }
 }
 
+impl Shroud {
+    /// One pixel per cell, with the cell value as the brightness.
+    pub fn image(&self) -> GrayImage {
+        GrayImage {
+            width: WIDTH,
+            height: HEIGHT,
+            pixels: self.cells.clone(),
+        }
+    }
+
+    pub fn set_image(&mut self, image: &GrayImage) -> crate::Result<()> {
+        if (image.width, image.height) != (WIDTH, HEIGHT) {
+            return Err(format!(
+                "Image must be {WIDTH}x{HEIGHT}, not {}x{}",
+                image.width, image.height
+            )
+            .into());
+        }
+
+        self.cells.copy_from_slice(&image.pixels);
+        Ok(())
+    }
+
+    /// Sets the cells within `radius` of (`x`, `y`) to `value`.
+    fn paint(&mut self, x: f32, y: f32, radius: f32, value: u8) {
+        let top = (y - radius).floor().max(0.0) as usize;
+        let bottom = ((y + radius).ceil() as usize).min(HEIGHT - 1);
+        let left = (x - radius).floor().max(0.0) as usize;
+        let right = ((x + radius).ceil() as usize).min(WIDTH - 1);
+
+        for cell_y in top..=bottom {
+            for cell_x in left..=right {
+                let (dx, dy) = (cell_x as f32 + 0.5 - x, cell_y as f32 + 0.5 - y);
+                if dx * dx + dy * dy <= radius * radius {
+                    self.cells[cell_y * WIDTH + cell_x] = value;
+                }
+            }
+        }
+    }
+}
+
+/// Brush settings, kept between frames in egui's memory.
+#[derive(Clone, Copy)]
+struct Brush {
+    radius: f32,
+    /// Value painted with the right mouse button, the left one reveals.
+    fog_value: u8,
+    zoom: f32,
+}
+
+/// Writes the cells of a shroud file to a grayscale PNG image.
+pub fn export(path: &Path, output: &Path) -> crate::Result<()> {
+    let shroud: Shroud = read_file(path, Codepage::default())?;
+    shroud.image().save(output)
+}
+
+/// Replaces the cells of a shroud file with a grayscale PNG image.
+pub fn import(path: &Path, image: &Path, output: &Path) -> crate::Result<()> {
+    let mut shroud: Shroud = read_file(path, Codepage::default())?;
+    shroud.set_image(&GrayImage::load(image)?)?;
+    write_file(output, &shroud, Codepage::default())
+}
+
 impl Inspector for Shroud {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
-        if ui.button("Remove fog of war").clicked() {
-            self.cells.fill(0);
+    fn show(&mut self, ui: &mut egui::Ui) {
+        if self.cells.len() != WIDTH * HEIGHT {
+            ui.label(format!("Expected {} cells", WIDTH * HEIGHT));
+            return;
         }
+
+        // The texture and statistics are only updated when the cells change.
+        let mut hasher = DefaultHasher::new();
+        self.cells.hash(&mut hasher);
+        let key = hasher.finish();
+
+        let cache_id = ui.id().with("shroud_texture");
+        let (texture, counts) = match ui
+            .data(|d| d.get_temp::<(u64, egui::TextureHandle, Vec<usize>)>(cache_id))
+        {
+            Some((cached_key, texture, counts)) if cached_key == key => (texture, counts),
+            _ => {
+                let image = egui::ColorImage::from_gray([WIDTH, HEIGHT], &self.cells);
+                let texture = ui
+                    .ctx()
+                    .load_texture("shroud", image, egui::TextureOptions::NEAREST);
+
+                let mut counts = vec![0; 256];
+                for &cell in &self.cells {
+                    counts[cell as usize] += 1;
+                }
+
+                ui.data_mut(|d| d.insert_temp(cache_id, (key, texture.clone(), counts.clone())));
+                (texture, counts)
+            }
+        };
+
+        let brush_id = ui.id().with("shroud_brush");
+        let mut brush = ui.data(|d| d.get_temp(brush_id)).unwrap_or_else(|| Brush {
+            radius: 8.0,
+            // The most common value other than 0 is most likely the fog.
+            fog_value: (1..=255)
+                .max_by_key(|&value| counts[value as usize])
+                .unwrap(),
+            zoom: 1.0,
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("Remove fog of war").clicked() {
+                self.cells.fill(0);
+            }
+            if ui.button("Cover everything").clicked() {
+                self.cells.fill(brush.fog_value);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(Slider::new(&mut brush.radius, 0.5..=64.0).text("brush radius"));
+            ui.label("fog value");
+            ui.add(DragValue::new(&mut brush.fog_value).range(1..=255));
+            ui.add(Slider::new(&mut brush.zoom, 0.5..=4.0).text("zoom"));
+        });
+        ui.label("Left mouse button reveals, right mouse button covers with fog.");
+
+        let total = self.cells.len();
+        let revealed = counts[0];
+        ui.label(format!(
+            "Revealed: {revealed} of {total} cells ({:.1}%)",
+            revealed as f64 / total as f64 * 100.0
+        ));
+        ui.collapsing("Values", |ui| {
+            egui::Grid::new(ui.id()).striped(true).show(ui, |ui| {
+                for (value, &count) in counts.iter().enumerate().filter(|(_, count)| **count > 0) {
+                    ui.label(value.to_string());
+                    ui.label(count.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+
+        egui::ScrollArea::both().show(ui, |ui| {
+            let response = ui.add(
+                egui::Image::new((texture.id(), texture.size_vec2() * brush.zoom))
+                    .sense(Sense::click_and_drag()),
+            );
+
+            if let Some(position) = response.interact_pointer_pos() {
+                let cell = (position - response.rect.min) / brush.zoom;
+                let value = ui.input(|i| {
+                    if i.pointer.primary_down() {
+                        Some(0)
+                    } else if i.pointer.secondary_down() {
+                        Some(brush.fog_value)
+                    } else {
+                        None
+                    }
+                });
+
+                if let Some(value) = value {
+                    self.paint(cell.x, cell.y, brush.radius, value);
+                }
+            }
+        });
+
+        ui.data_mut(|d| d.insert_temp(brush_id, brush));
     }
 }
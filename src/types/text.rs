@@ -1,79 +1,44 @@
 //! text.cmp
 
-use crate::{
-    editor::{Inspector, property, struct_ui},
-    types::Binary,
-};
+use crate::{editor::Inspector, error::ParseResultExt, types::Binary};
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, Binary, Inspector)]
 pub struct Text {
     entries: Vec<TextEntry>,
 }
 
-#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[derive(Default, serde::Serialize, serde::Deserialize, Binary, Inspector)]
 struct TextEntry {
     unknown0: u32,
     entries: Vec<TextEntry2>,
 }
 
-#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[derive(Default, serde::Serialize, serde::Deserialize, Inspector)]
 struct TextEntry2 {
     id: u32,
     text1: String,
     text2: String,
 }
 
-impl Binary for Text {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
-    where
-        Self: Sized,
-    {
-        Ok(Self {
-            entries: Vec::from_bytes(reader)?,
-        })
-    }
-
-    fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
-        self.entries.to_bytes(writer);
-    }
-}
-
-impl Binary for TextEntry {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
-    where
-        Self: Sized,
-    {
-        Ok(TextEntry {
-            unknown0: u32::from_bytes(reader)?,
-            entries: Vec::from_bytes(reader)?,
-        })
-    }
-
-    fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
-        self.unknown0.to_bytes(writer);
-        self.entries.to_bytes(writer);
-    }
-}
-
 impl Binary for TextEntry2 {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
-        let id = u32::from_bytes(reader)?;
+        let id = u32::from_bytes(reader).field("id")?;
 
         // Strings are null-terminated, but the null byte is not included in the length.
-        let text1 = String::from_bytes(reader)?;
+        let text1 = String::from_bytes(reader).field("text1")?;
 
         if !text1.is_empty() {
             // There is no null byte if the string is empty.
-            reader.skip(1);
+            reader.skip(1).field("text1")?;
         }
 
-        let text2 = String::from_bytes(reader)?;
+        let text2 = String::from_bytes(reader).field("text2")?;
 
         if !text2.is_empty() {
-            reader.skip(1);
+            reader.skip(1).field("text2")?;
         }
 
         Ok(Self { id, text1, text2 })
@@ -96,29 +61,26 @@ impl Binary for TextEntry2 {
     }
 }
 
-impl Inspector for Text {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
-        struct_ui(ui, |ui| {
-            property("entries", &mut self.entries, ui);
-        });
-    }
-}
-
-impl Inspector for TextEntry {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
-        struct_ui(ui, |ui| {
-            property("unknown0", &mut self.unknown0, ui);
-            property("entries", &mut self.entries, ui);
-        });
-    }
-}
-
-impl Inspector for TextEntry2 {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
-        struct_ui(ui, |ui| {
-            property("id", &mut self.id, ui);
-            property("text1", &mut self.text1, ui);
-            property("text2", &mut self.text2, ui);
-        });
+#[cfg(test)]
+mod tests {
+    use crate::{codepage::Codepage, error::ParseErrorKind, types::FormatType};
+
+    #[test]
+    fn truncated_file() {
+        // Text entry whose second string is missing its null terminator.
+        let mut bytes = [1u32, 0, 1, 5, 0, 1]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>();
+        bytes.push(b'a');
+
+        let error = FormatType::Text
+            .load(&bytes, Codepage::default())
+            .err()
+            .expect("truncated file must fail to load");
+        assert!(
+            matches!(error.kind, ParseErrorKind::UnexpectedEof { .. }),
+            "{error}"
+        );
     }
 }
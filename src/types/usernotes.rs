@@ -1,11 +1,12 @@
 //! usernotes.bin, mapflags.000
 
 use crate::{
-    editor::{Inspector, property, struct_ui},
+    editor::Inspector,
+    error::ParseResultExt,
     types::{Binary, CStringWithLength},
 };
 
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, Inspector)]
 pub struct Notes {
     user_notes: Vec<Note>,
     notes: Vec<Note>,
@@ -13,7 +14,7 @@ pub struct Notes {
     unknown1: u32,
 }
 
-#[derive(Default, serde::Serialize, serde::Deserialize)]
+#[derive(Default, serde::Serialize, serde::Deserialize, Binary, Inspector)]
 struct Note {
     position_x: i16,
     position_y: i16,
@@ -26,32 +27,36 @@ struct Note {
 }
 
 impl Binary for Notes {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
-        let user_notes_count = u32::from_bytes(reader)?;
+        let user_notes_count = u32::from_bytes(reader).field("user_notes_count")?;
         // User notes' buffer length. We will calculate it during serialization.
-        reader.skip(4);
+        reader.skip(4).field("user_notes_buffer_length")?;
 
         let mut user_notes = Vec::with_capacity(user_notes_count as usize);
 
         for _ in 0..user_notes_count {
-            user_notes.push(Note::from_bytes(reader)?);
+            user_notes.push(
+                Note::from_bytes(reader)
+                    .index(user_notes.len())
+                    .field("user_notes")?,
+            );
         }
 
-        let note_count = reader.read_u32()?;
+        let note_count = reader.read_u32().field("note_count")?;
         // Notes' buffer length. We will calculate it during serialization.
-        reader.skip(4);
+        reader.skip(4).field("notes_buffer_length")?;
 
         let mut notes = Vec::with_capacity(note_count as usize);
 
         for _ in 0..note_count {
-            notes.push(Note::from_bytes(reader)?);
+            notes.push(Note::from_bytes(reader).index(notes.len()).field("notes")?);
         }
 
-        let unknown0 = reader.read_u32()?;
-        let unknown1 = reader.read_u32()?;
+        let unknown0 = reader.read_u32().field("unknown0")?;
+        let unknown1 = reader.read_u32().field("unknown1")?;
 
         Ok(Self {
             user_notes,
@@ -67,7 +72,7 @@ impl Binary for Notes {
         let mut user_notes_buffer_length = 0u32;
 
         for note in &self.user_notes {
-            user_notes_buffer_length += (note.text.inner.count_bytes() + 1 + 28) as u32
+            user_notes_buffer_length += (note.text.encoded_len(writer.codepage()) + 28) as u32
         }
 
         user_notes_buffer_length.to_bytes(writer);
@@ -81,7 +86,7 @@ impl Binary for Notes {
         let mut notes_buffer_length = 0u32;
 
         for note in &self.notes {
-            notes_buffer_length += (note.text.inner.count_bytes() + 1 + 28) as u32
+            notes_buffer_length += (note.text.encoded_len(writer.codepage()) + 28) as u32
         }
 
         notes_buffer_length.to_bytes(writer);
@@ -94,58 +99,3 @@ impl Binary for Notes {
         self.unknown1.to_bytes(writer);
     }
 }
-
-impl Binary for Note {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
-    where
-        Self: Sized,
-    {
-        Ok(Self {
-            position_x: i16::from_bytes(reader)?,
-            position_y: i16::from_bytes(reader)?,
-            unknown0: u32::from_bytes(reader)?,
-            is_visible: u32::from_bytes(reader)?,
-            id: u32::from_bytes(reader)?,
-            unknown1: u32::from_bytes(reader)?,
-            text: CStringWithLength::from_bytes(reader)?,
-            unknown2: u32::from_bytes(reader)?,
-        })
-    }
-
-    fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
-        self.position_x.to_bytes(writer);
-        self.position_y.to_bytes(writer);
-        self.unknown0.to_bytes(writer);
-        self.is_visible.to_bytes(writer);
-        self.id.to_bytes(writer);
-        self.unknown1.to_bytes(writer);
-        self.text.to_bytes(writer);
-        self.unknown2.to_bytes(writer);
-    }
-}
-
-impl Inspector for Notes {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
-        struct_ui(ui, |ui| {
-            property("user_notes", &mut self.user_notes, ui);
-            property("notes", &mut self.notes, ui);
-            property("unknown1", &mut self.unknown0, ui);
-            property("unknown2", &mut self.unknown1, ui);
-        });
-    }
-}
-
-impl Inspector for Note {
-    fn show(&mut self, ui: &mut eframe::egui::Ui) {
-        struct_ui(ui, |ui| {
-            property("position_x", &mut self.position_x, ui);
-            property("position_y", &mut self.position_y, ui);
-            property("unknown0", &mut self.unknown0, ui);
-            property("is_visible", &mut self.is_visible, ui);
-            property("id", &mut self.id, ui);
-            property("unknown1", &mut self.unknown1, ui);
-            property("text", &mut self.text, ui);
-            property("unknown2", &mut self.unknown2, ui);
-        });
-    }
-}
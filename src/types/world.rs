@@ -1,17 +1,42 @@
 //! world.xN
 
-use crate::{editor::Inspector, types::Binary};
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use eframe::egui::{
+    self, Color32, PointerButton, Rect, Sense, Stroke, StrokeKind, Vec2, pos2, vec2,
+};
+
+use crate::{
+    editor::{Inspector, property, struct_ui},
+    error::ParseResultExt,
+    types::Binary,
+};
 
 const WIDTH: usize = 512;
 const HEIGHT: usize = 1024;
 
+/// Height of the map view in the inspector, in points.
+const VIEW_HEIGHT: f32 = 600.0;
+
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct World {
     cells: Box<[Cell]>,
     unknown1: u32,
+    /// Changes whenever the cells do, so the map texture knows when to update.
+    #[serde(skip, default = "next_generation")]
+    generation: u64,
+}
+
+/// Returns a number that no other version of a world has had.
+fn next_generation() -> u64 {
+    static GENERATION: AtomicU64 = AtomicU64::new(0);
+    GENERATION.fetch_add(1, Ordering::Relaxed)
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Default, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Default, Clone, Hash, Inspector)]
 struct Cell {
     image_index1: i16,
     image_index2: i16,
@@ -22,7 +47,7 @@ struct Cell {
     unknown_vec: Vec<Unknown0>,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Default, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Default, Clone, Hash, Binary, Inspector)]
 struct Unknown0 {
     unknown0: u16,
     unknown1: u16,
@@ -31,30 +56,42 @@ struct Unknown0 {
 }
 
 impl Binary for World {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
         // Row offsets, will be calculated during serialization.
-        reader.skip(HEIGHT * 4);
+        reader.skip(HEIGHT * 4).field("row_offsets")?;
 
         let mut cells = vec![Cell::default(); WIDTH * HEIGHT];
 
         for y in 0..HEIGHT {
             // Cell offsets, will be calculated during serialization.
-            reader.skip(WIDTH * 2);
+            reader.skip(WIDTH * 2).field("cell_offsets")?;
             for x in 0..WIDTH {
-                cells[y * WIDTH + x] = Cell::from_bytes(reader)?;
+                cells[y * WIDTH + x] = Cell::from_bytes(reader)
+                    .index(y * WIDTH + x)
+                    .field("cells")?;
             }
         }
 
         Ok(Self {
             cells: cells.into_boxed_slice(),
-            unknown1: reader.read_u32()?,
+            unknown1: reader.read_u32().field("unknown1")?,
+            generation: next_generation(),
         })
     }
 
     fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
+        if self.cells.len() != WIDTH * HEIGHT {
+            writer.fail(format!(
+                "cells must have {} elements, not {}",
+                WIDTH * HEIGHT,
+                self.cells.len()
+            ));
+            return;
+        }
+
         let mut row_offsets = Vec::<u32>::with_capacity(HEIGHT);
         let mut row_writer = crate::buffer::BufferWriter::new();
 
@@ -64,44 +101,53 @@ impl Binary for World {
             let mut cell_writer = crate::buffer::BufferWriter::new();
 
             for x in 0..WIDTH {
-                (cell_writer.len() as u16).to_bytes(&mut row_writer);
+                match u16::try_from(cell_writer.len()) {
+                    Ok(offset) => offset.to_bytes(&mut row_writer),
+                    Err(_) => {
+                        row_writer.fail(format!("Cells of row {y} are larger than 64 KiB"));
+                    }
+                }
 
                 let cell = &self.cells[y * WIDTH + x];
                 cell.to_bytes(&mut cell_writer);
             }
 
-            row_writer.write_bytes(&cell_writer.finish());
+            row_writer.append(cell_writer);
         }
 
         for offset in row_offsets {
             offset.to_bytes(writer);
         }
 
-        writer.write_bytes(&row_writer.finish());
+        writer.append(row_writer);
         self.unknown1.to_bytes(writer);
     }
 }
 
 impl Binary for Cell {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
+    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::ParseResult<Self>
     where
         Self: Sized,
     {
-        let image_index1 = reader.read_i16()?;
-        let image_index2 = reader.read_i16()?;
+        let image_index1 = reader.read_i16().field("image_index1")?;
+        let image_index2 = reader.read_i16().field("image_index2")?;
 
-        let unknown0 = reader.read_u16()?;
+        let unknown0 = reader.read_u16().field("unknown0")?;
 
-        let unknown_len = reader.read_u8()?;
-        let unknown1 = reader.read_u8()?;
+        let unknown_len = reader.read_u8().field("unknown_len")?;
+        let unknown1 = reader.read_u8().field("unknown1")?;
 
-        let unknown2 = reader.read_u32()?;
-        let unknown3 = reader.read_u32()?;
+        let unknown2 = reader.read_u32().field("unknown2")?;
+        let unknown3 = reader.read_u32().field("unknown3")?;
 
         let mut unknown_vec = Vec::with_capacity(unknown_len as usize);
 
         for _ in 0..unknown_len {
-            unknown_vec.push(Unknown0::from_bytes(reader)?);
+            unknown_vec.push(
+                Unknown0::from_bytes(reader)
+                    .index(unknown_vec.len())
+                    .field("unknown_vec")?,
+            );
         }
 
         Ok(Self {
@@ -119,7 +165,15 @@ impl Binary for Cell {
         self.image_index1.to_bytes(writer);
         self.image_index2.to_bytes(writer);
         self.unknown0.to_bytes(writer);
-        (self.unknown_vec.len() as u8).to_bytes(writer);
+        match u8::try_from(self.unknown_vec.len()) {
+            Ok(len) => len.to_bytes(writer),
+            Err(_) => {
+                writer.fail(format!(
+                    "unknown_vec has {} elements, too many for a u8 length",
+                    self.unknown_vec.len()
+                ));
+            }
+        }
         self.unknown1.to_bytes(writer);
         self.unknown2.to_bytes(writer);
         self.unknown3.to_bytes(writer);
@@ -130,35 +184,241 @@ impl Binary for Cell {
     }
 }
 
-impl Binary for Unknown0 {
-    fn from_bytes(reader: &mut crate::buffer::BufferReader) -> crate::Result<Self>
-    where
-        Self: Sized,
-    {
-        Ok(Self {
-            unknown0: reader.read_u16()?,
-            unknown1: reader.read_u16()?,
-            unknown2: reader.read_u16()?,
-            unknown3: reader.read_u16()?,
-        })
+/// Field of the cells that the map is coloured by.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Inspector)]
+enum Layer {
+    #[default]
+    #[inspector(label = "image_index1")]
+    ImageIndex1,
+    #[inspector(label = "image_index2")]
+    ImageIndex2,
+    #[inspector(label = "unknown0")]
+    Unknown0,
+    #[inspector(label = "unknown1")]
+    Unknown1,
+    #[inspector(label = "unknown2")]
+    Unknown2,
+    #[inspector(label = "unknown3")]
+    Unknown3,
+    #[inspector(label = "unknown_vec length")]
+    UnknownVecLength,
+}
+
+impl Layer {
+    fn value(self, cell: &Cell) -> i64 {
+        match self {
+            Layer::ImageIndex1 => cell.image_index1 as i64,
+            Layer::ImageIndex2 => cell.image_index2 as i64,
+            Layer::Unknown0 => cell.unknown0 as i64,
+            Layer::Unknown1 => cell.unknown1 as i64,
+            Layer::Unknown2 => cell.unknown2 as i64,
+            Layer::Unknown3 => cell.unknown3 as i64,
+            Layer::UnknownVecLength => cell.unknown_vec.len() as i64,
+        }
     }
+}
 
-    fn to_bytes(&self, writer: &mut crate::buffer::BufferWriter) {
-        self.unknown0.to_bytes(writer);
-        self.unknown1.to_bytes(writer);
-        self.unknown2.to_bytes(writer);
-        self.unknown3.to_bytes(writer);
+/// Zero is black, other values get colours that are far apart for nearby
+/// values, so that neighbouring indices can be told apart.
+fn value_color(value: i64) -> Color32 {
+    if value == 0 {
+        return Color32::BLACK;
     }
+
+    let hue = (value as f32 * 0.618_034).rem_euclid(1.0);
+    egui::ecolor::Hsva::new(hue, 0.7, 0.9, 1.0).into()
 }
 
-impl Inspector for World {
-    fn show(&mut self, _ui: &mut eframe::egui::Ui) {}
+/// State of the map view in the inspector.
+#[derive(Clone)]
+struct MapView {
+    layer: Layer,
+    /// Cell at the top left corner of the view.
+    offset: Vec2,
+    /// Size of a cell in points.
+    zoom: f32,
+    /// Index of the cell shown below the map.
+    selected: Option<usize>,
+}
+
+impl Default for MapView {
+    fn default() -> Self {
+        Self {
+            layer: Layer::default(),
+            offset: Vec2::ZERO,
+            zoom: 1.0,
+            selected: None,
+        }
+    }
+}
+
+impl World {
+    /// The texture is only made again when the layer or the cells change.
+    /// Hashing all cells every frame would be too slow, so the generation of
+    /// the world is used instead.
+    fn texture(&self, layer: Layer, ui: &egui::Ui) -> egui::TextureHandle {
+        let texture_id = egui::Id::new("world_texture");
+        let key = (layer, self.generation);
+
+        let cached = ui.data(|d| d.get_temp::<((Layer, u64), egui::TextureHandle)>(texture_id));
+        match cached {
+            Some((cached_key, texture)) if cached_key == key => texture,
+            _ => {
+                let pixels = self
+                    .cells
+                    .iter()
+                    .map(|cell| value_color(layer.value(cell)))
+                    .collect();
+                let image = egui::ColorImage::new([WIDTH, HEIGHT], pixels);
+                let texture = ui
+                    .ctx()
+                    .load_texture("world", image, egui::TextureOptions::NEAREST);
+                ui.data_mut(|d| d.insert_temp(texture_id, (key, texture.clone())));
+                texture
+            }
+        }
+    }
+
+    fn show_map(&self, view: &mut MapView, ui: &mut egui::Ui) {
+        let texture = self.texture(view.layer, ui);
+
+        let (rect, response) = ui.allocate_exact_size(
+            vec2(ui.available_width(), VIEW_HEIGHT),
+            Sense::click_and_drag(),
+        );
+
+        if response.dragged() {
+            view.offset -= response.drag_delta() / view.zoom;
+        }
+
+        let hovered_cell = response.hover_pos().map(|position| {
+            let cell = view.offset + (position - rect.min) / view.zoom;
+            (cell.x.floor(), cell.y.floor())
+        });
+
+        // Zoom around the cursor, and keep the scroll from reaching the parent
+        // scroll area.
+        if let Some(position) = response.hover_pos() {
+            let scroll = ui.input_mut(|i| std::mem::take(&mut i.smooth_scroll_delta).y);
+
+            if scroll != 0.0 {
+                let anchor = view.offset + (position - rect.min) / view.zoom;
+                view.zoom = (view.zoom * (scroll / 200.0).exp()).clamp(0.25, 64.0);
+                view.offset = anchor - (position - rect.min) / view.zoom;
+            }
+        }
+
+        let cell_index = |(x, y): (f32, f32)| {
+            ((0.0..WIDTH as f32).contains(&x) && (0.0..HEIGHT as f32).contains(&y))
+                .then(|| y as usize * WIDTH + x as usize)
+        };
+        let hovered = hovered_cell.and_then(cell_index);
+
+        if response.clicked_by(PointerButton::Primary) {
+            view.selected = hovered;
+        }
+
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, Color32::from_gray(20));
+
+        let map_rect = Rect::from_min_size(
+            rect.min - view.offset * view.zoom,
+            vec2(WIDTH as f32, HEIGHT as f32) * view.zoom,
+        );
+        painter.image(
+            texture.id(),
+            map_rect,
+            Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+
+        if let Some(index) = view.selected {
+            let position = vec2((index % WIDTH) as f32, (index / WIDTH) as f32);
+            painter.rect_stroke(
+                Rect::from_min_size(map_rect.min + position * view.zoom, Vec2::splat(view.zoom)),
+                0.0,
+                Stroke::new(2.0, Color32::WHITE),
+                StrokeKind::Outside,
+            );
+        }
+
+        let layer = view.layer;
+        ui.label(match hovered {
+            Some(index) => format!(
+                "x: {}, y: {}, value: {}",
+                index % WIDTH,
+                index / WIDTH,
+                layer.value(&self.cells[index])
+            ),
+            None => "Drag to pan, scroll to zoom, click a cell to select it.".to_owned(),
+        });
+    }
 }
 
-impl Inspector for Cell {
-    fn show(&mut self, _ui: &mut eframe::egui::Ui) {}
+impl Inspector for World {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        let view_id = egui::Id::new("world_view");
+        let mut view: MapView = ui.data(|d| d.get_temp(view_id)).unwrap_or_default();
+
+        struct_ui(ui, |ui| {
+            property("unknown1", &mut self.unknown1, ui);
+            property("colour by", &mut view.layer, ui);
+        });
+
+        // Worlds made from JSON can have any number of cells.
+        if self.cells.len() != WIDTH * HEIGHT {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!(
+                    "The map must have {} cells, but has {}",
+                    WIDTH * HEIGHT,
+                    self.cells.len()
+                ),
+            );
+            return;
+        }
+
+        if ui.button("Reset view").clicked() {
+            view.offset = Vec2::ZERO;
+            view.zoom = 1.0;
+        }
+
+        self.show_map(&mut view, ui);
+        ui.data_mut(|d| d.insert_temp(view_id, view.clone()));
+
+        let Some(index) = view.selected else {
+            return;
+        };
+
+        ui.separator();
+        ui.heading(format!("Cell x: {}, y: {}", index % WIDTH, index / WIDTH));
+
+        // Edits change the colours of the map.
+        let hash = |cell: &Cell| {
+            let mut hasher = DefaultHasher::new();
+            cell.hash(&mut hasher);
+            hasher.finish()
+        };
+        let cell = &mut self.cells[index];
+        let before = hash(cell);
+        cell.show(ui);
+        if hash(cell) != before {
+            self.generation = next_generation();
+        }
+    }
 }
 
-impl Inspector for Unknown0 {
-    fn show(&mut self, _ui: &mut eframe::egui::Ui) {}
+#[cfg(test)]
+mod tests {
+    use super::World;
+    use crate::{buffer::BufferWriter, types::Binary};
+
+    #[test]
+    fn wrong_cell_count() {
+        let world: World = serde_json::from_str(r#"{"cells": [], "unknown1": 0}"#).unwrap();
+
+        let mut writer = BufferWriter::new();
+        world.to_bytes(&mut writer);
+        assert!(writer.finish().is_err());
+    }
 }